@@ -85,47 +85,101 @@ mod util;
 pub use stream::EventStream;
 pub use util::*;
 
-use async_std::{os::unix::net::UnixStream, prelude::*};
+use async_std::{
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    prelude::*,
+    stream::Stream,
+};
 use serde::de::DeserializeOwned;
-use std::io;
+use std::{
+    io,
+    os::unix::{io::RawFd, net::UnixStream as StdUnixStream, prelude::FromRawFd},
+    path::Path,
+};
 
-/// Newtype wrapper for `UnixStream` that implements i3's IPC
+/// Newtype wrapper for a stream that implements i3's IPC. Defaults to
+/// `UnixStream`, but any `Read + Write` transport can be used (see
+/// [from_stream](struct.I3.html#method.from_stream))
 #[derive(Debug)]
-pub struct I3 {
-    stream: UnixStream,
+pub struct I3<S = UnixStream> {
+    stream: S,
+    max_frame_len: usize,
 }
 
-// Implement `Future` for [I3](struct.I3.html) so it can be polled into a ready
-// `UnixStream`
+impl<S> I3Protocol for I3<S> {}
+
 impl I3 {
+    /// Connects to I3 over `UnixStream`
+    pub async fn connect() -> io::Result<Self> {
+        Ok(I3::from_stream(UnixStream::connect(socket_path()?).await?))
+    }
+
+    /// Connects to the i3 (or sway) socket at `path`, skipping socket discovery
+    pub async fn connect_to<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(I3::from_stream(UnixStream::connect(path.as_ref()).await?))
+    }
+
+    /// Wraps an already connected std `UnixStream`. The stream is put into
+    /// non-blocking mode.
+    pub fn from_std(stream: StdUnixStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        Ok(I3::from_stream(stream.into()))
+    }
+
+    /// Wraps an inherited or socket-activated file descriptor
+    ///
+    /// # Safety
+    ///
+    /// `fd` must be an open unix stream socket connected to i3, and ownership
+    /// of it is transferred to the returned value.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> io::Result<Self> {
+        Self::from_std(StdUnixStream::from_raw_fd(fd))
+    }
+}
+
+impl<S> I3<S>
+where
+    S: Read + Write + Unpin,
+{
+    /// Use any `Read + Write` transport to talk to i3, e.g. a stream wrapped
+    /// with logging
+    pub fn from_stream(stream: S) -> Self {
+        I3 {
+            stream,
+            max_frame_len: frame::DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    /// Reject replies and events with payloads over `len` bytes, defaults to
+    /// [DEFAULT_MAX_FRAME_LEN](frame/constant.DEFAULT_MAX_FRAME_LEN.html)
+    pub fn set_max_frame_len(&mut self, len: usize) {
+        self.max_frame_len = len;
+    }
+
+    /// Consumes `I3`, returning the underlying stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
     /// Sends a message and payload, used for `get_*` commands and `run_command`
     async fn _send_msg<P>(&mut self, msg: msg::Msg, payload: Option<P>) -> io::Result<()>
     where
         P: AsRef<str>,
     {
-        let buf = self.stream._encode_msg(msg, payload);
+        let buf = self._encode_msg(msg, payload);
         self.stream.write_all(&buf).await
     }
 
     async fn _decode_msg(&mut self) -> io::Result<(u32, Vec<u8>)> {
-        let mut init = [0_u8; 14];
+        let mut init = [0_u8; frame::HEADER_LEN];
         self.stream.read_exact(&mut init).await?;
+        let header = frame::Header::parse_with_limit(&init, self.max_frame_len)?;
 
-        assert!(&init[0..6] == MAGIC.as_bytes(), "Magic str not received");
-        let payload_len = u32::from_ne_bytes([init[6], init[7], init[8], init[9]]) as usize;
-        let msg_type = u32::from_ne_bytes([init[10], init[11], init[12], init[13]]);
-
-        let mut payload = vec![0_u8; payload_len];
+        let mut payload = vec![0_u8; header.len as usize];
         self.stream.read_exact(&mut payload).await?;
 
-        Ok((msg_type, payload))
-    }
-
-    /// Connects to I3 over `UnixStream`
-    pub async fn connect() -> io::Result<Self> {
-        Ok(I3 {
-            stream: UnixStream::connect(socket_path()?).await?,
-        })
+        Ok((header.msg_type, payload))
     }
 
     pub async fn send_msg_body<P>(&mut self, msg: msg::Msg, payload: P) -> io::Result<()>
@@ -145,10 +199,7 @@ impl I3 {
         D: DeserializeOwned,
     {
         let (msg_type, payload) = self._decode_msg().await?;
-        Ok(MsgResponse {
-            msg_type: msg_type.into(),
-            body: serde_json::from_slice(&payload[..])?,
-        })
+        MsgResponse::new(msg_type, payload)
     }
 
     /// Like `read_msg` but for `event::Event`
@@ -168,117 +219,166 @@ impl I3 {
         self.read_msg().await
     }
 
+    /// Send any [Request](request/trait.Request.html) and read its reply. The
+    /// reply type is determined by the request, a reply to a different
+    /// message type is returned as an error.
+    pub async fn request<R: request::Request>(&mut self, req: R) -> io::Result<R::Reply> {
+        let buf = req.encode()?;
+        self.stream.write_all(&buf).await?;
+        let (msg_type, payload) = self._decode_msg().await?;
+        R::decode_reply(msg_type, &payload)
+    }
+
     /// Returns a Future that will send a [Subscribe](event/enum.Subscribe.html)
     /// message to i3 along with a list of events to listen to.
     pub async fn subscribe<E>(&mut self, events: E) -> io::Result<reply::Success>
     where
         E: AsRef<[event::Subscribe]>,
     {
-        let sub_json = serde_json::to_string(events.as_ref())?;
-        self.send_msg_body(msg::Msg::Subscribe, sub_json).await?;
-        Ok(self.read_msg::<reply::Success>().await?.body)
+        self.request(request::Subscribe(events.as_ref().to_vec()))
+            .await
     }
 
     /// Provides a type that implements `Stream` so you can `await` events in a
     /// loop
-    pub fn listen(self) -> EventStream {
-        EventStream::new(self.stream)
+    pub fn listen(self) -> EventStream<S> {
+        EventStream::with_max_frame_len(self.stream, self.max_frame_len)
     }
 
     /// Run an arbitrary command on i3. Response is a `Vec` of success
     /// true/false.
-    pub async fn run_command<S: AsRef<str>>(
+    pub async fn run_command<P: AsRef<str>>(
         &mut self,
-        payload: S,
+        payload: P,
     ) -> io::Result<Vec<reply::Success>> {
-        self.send_msg_body(msg::Msg::RunCommand, payload).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::RunCommand(payload.as_ref().to_owned()))
+            .await
+    }
+
+    /// Run several commands, pairing each with i3's reply to it. Normally the
+    /// commands are joined and sent as one message, which i3 runs to the end
+    /// whatever fails. With `stop_on_failure` they are sent one at a time
+    /// instead, and nothing after the first failure is run.
+    pub async fn run_batch(
+        &mut self,
+        commands: Vec<command::Command>,
+        stop_on_failure: bool,
+    ) -> io::Result<Vec<command::CommandResult>> {
+        if !stop_on_failure {
+            let results = self.run_command(command::join(&commands)?).await?;
+            return command::zip(commands, results);
+        }
+        let mut out = Vec::with_capacity(commands.len());
+        for cmd in commands {
+            let payload = command::join(std::slice::from_ref(&cmd))?;
+            let results = self.run_command(payload).await?;
+            out.extend(command::zip(vec![cmd], results)?);
+            if out.last().is_some_and(|r| !r.is_success()) {
+                break;
+            }
+        }
+        Ok(out)
     }
 
     /// Future for getting the current
     /// [Workspaces](../reply/struct.Workspace.html), sends
     /// [Workspaces](../msg/enum.Msg.html#variant.Workspaces)
     pub async fn get_workspaces(&mut self) -> io::Result<reply::Workspaces> {
-        self.send_msg(msg::Msg::Workspaces).await?;
-        let resp: MsgResponse<Vec<reply::Workspace>> = self.read_msg().await?;
-        Ok(resp.body)
+        self.request(request::Workspaces).await
     }
 
     /// Future that gets all [Outputs](../reply/struct.Outputs.html), sends
     /// [Outputs](../msg/enum.Msg.html#variant.Outputs)
     pub async fn get_outputs(&mut self) -> io::Result<reply::Outputs> {
-        self.send_msg(msg::Msg::Outputs).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Outputs).await
     }
 
     /// Future to get complete [Node](../reply/struct.Node.html), sends
     /// [Tree](../msg/enum.Msg.html#variant.Tree)
     pub async fn get_tree(&mut self) -> io::Result<reply::Node> {
-        self.send_msg(msg::Msg::Tree).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Tree).await
     }
 
     /// Get all [Marks](../reply/struct.Marks.html), sends
     /// [Marks](../msg/enum.Msg.html#variant.Marks)
     pub async fn get_marks(&mut self) -> io::Result<reply::Marks> {
-        self.send_msg(msg::Msg::Marks).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Marks).await
     }
 
     /// Future to get all [BarIds](../reply/struct.BarIds.html), sends
     /// [BarConfig](../msg/enum.Msg.html#variant.BarConfig)
     pub async fn get_bar_ids(&mut self) -> io::Result<reply::BarIds> {
-        self.send_msg(msg::Msg::BarConfig).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::BarIds).await
     }
 
     /// Future to get configs associated with a bar id responds with
     /// [BarConfig](../reply/struct.BarConfig.html), sends
     /// [BarConfig](../msg/enum.Msg.html#variant.BarConfig)
-    pub async fn get_bar_config<S: AsRef<str>>(
+    pub async fn get_bar_config<P: AsRef<str>>(
         &mut self,
-        bar_id: S,
+        bar_id: P,
     ) -> io::Result<reply::BarConfig> {
-        self.send_msg_body(msg::Msg::BarConfig, bar_id).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::BarConfig(bar_id.as_ref().to_owned()))
+            .await
     }
 
     /// Get i3 version
     pub async fn get_version(&mut self) -> io::Result<reply::Version> {
-        self.send_msg(msg::Msg::Version).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Version).await
     }
 
     /// Future to get [BindingModes](../reply/struct.BindingModes.html), sends
     /// [BindingModes](../msg/enum.Msg.html#variant.BindingModes)
     pub async fn get_binding_modes(&mut self) -> io::Result<reply::BindingModes> {
-        self.send_msg(msg::Msg::BindingModes).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::BindingModes).await
     }
 
     /// Future for [Config](../reply/struct.Config.html), sends
     /// [Config](../msg/enum.Msg.html#variant.Config)
     pub async fn get_config(&mut self) -> io::Result<reply::Config> {
-        self.send_msg(msg::Msg::Config).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Config).await
     }
 
     /// Future sends [Tick](../msg/enum.Msg.html#variant.Tick)
     pub async fn get_tick(&mut self) -> io::Result<reply::Success> {
-        self.send_msg(msg::Msg::Tick).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Tick::default()).await
+    }
+
+    /// Future sends [Tick](../msg/enum.Msg.html#variant.Tick) with `payload`,
+    /// which subscribers receive as a [TickData](../event/struct.TickData.html)
+    pub async fn send_tick<P: AsRef<str>>(&mut self, payload: P) -> io::Result<reply::Success> {
+        self.request(request::Tick(payload.as_ref().to_owned()))
+            .await
+    }
+
+    /// Send a uniquely named tick and wait for it to come back on `events`, a
+    /// stream from another connection subscribed to
+    /// [Tick](../event/enum.Subscribe.html#variant.Tick). i3 delivers events
+    /// in order, so once this resolves every event caused by commands sent
+    /// before it has been received. Those events are returned rather than
+    /// dropped. A tick i3 rejects is an `InvalidData` error.
+    pub async fn barrier<E>(&mut self, events: &mut E) -> io::Result<Vec<event::Event>>
+    where
+        E: Stream<Item = io::Result<event::Event>> + Unpin,
+    {
+        let mut barrier = request::Barrier::new();
+        barrier.check(&self.request(barrier.tick()).await?)?;
+        while let Some(evt) = events.next().await {
+            if barrier.push(evt?) {
+                return Ok(barrier.into_events());
+            }
+        }
+        Err(request::Barrier::closed())
     }
 
     /// Future [Sync](../msg/enum.Msg.html#variant.Sync)
     pub async fn get_sync(&mut self) -> io::Result<reply::Success> {
-        self.send_msg(msg::Msg::Sync).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Sync).await
     }
 
     /// Future to get [BindingState](../reply/struct.BindingState.html), sends
     /// [BindingState](../msg/enum.Msg.html#variant.BindingState)
     pub async fn get_binding_state(&mut self) -> io::Result<reply::BindingState> {
-        self.send_msg(msg::Msg::BindingState).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::BindingState).await
     }
 }
@@ -1,30 +1,123 @@
-use async_std::{io::ReadExt, os::unix::net::UnixStream};
-use i3ipc_types::{decode_event, event, MAGIC};
-use std::io;
+use async_std::{future, io::Read, os::unix::net::UnixStream, stream::Stream};
+use i3ipc_types::{criteria::Criteria, event, frame, reply};
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Instant,
+};
 
-pub struct EventStream {
-    inner: UnixStream,
+/// Stream of [Event](../event/enum.Event.html)s received after subscribing.
+/// Bytes are buffered internally, so a pending read can be dropped (in a
+/// `select!` for instance) without losing part of a frame.
+#[derive(Debug)]
+pub struct EventStream<S = UnixStream> {
+    inner: S,
+    reader: frame::EventReader,
 }
 
-impl EventStream {
-    pub fn new(inner: UnixStream) -> Self {
-        Self { inner }
+impl<S: Read + Unpin> EventStream<S> {
+    pub fn new(inner: S) -> Self {
+        Self::with_max_frame_len(inner, frame::DEFAULT_MAX_FRAME_LEN)
     }
 
-    // doesn't actually use the Stream trait because we need to use `read_exact`
-    //  and I don't feel like doing all the logic for that.
-    // Internally uses read_exact, not cancel-safe
+    /// Like `new` but rejects events with payloads over `len` bytes
+    pub fn with_max_frame_len(inner: S, len: usize) -> Self {
+        Self {
+            inner,
+            reader: frame::EventReader::with_max_frame_len(len),
+        }
+    }
+
+    /// Wait for the next event. Unlike `StreamExt::next` this returns an
+    /// `UnexpectedEof` error rather than `None` once i3 closes the socket.
+    /// Cancel-safe.
     pub async fn next(&mut self) -> io::Result<event::Event> {
-        let mut init = [0_u8; 14];
-        self.inner.read_exact(&mut init).await?;
+        future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx))
+            .await
+            .unwrap_or_else(|| {
+                Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "event stream closed",
+                ))
+            })
+    }
 
-        assert!(&init[0..6] == MAGIC.as_bytes(), "Magic str not received");
-        let payload_len = u32::from_ne_bytes([init[6], init[7], init[8], init[9]]) as usize;
-        let msg_type = u32::from_ne_bytes([init[10], init[11], init[12], init[13]]);
+    /// Wait for the first event `predicate` returns true for. Returns an
+    /// `ErrorKind::TimedOut` error once `deadline` passes.
+    pub async fn wait_for<F>(
+        &mut self,
+        mut predicate: F,
+        deadline: Instant,
+    ) -> io::Result<event::Event>
+    where
+        F: FnMut(&event::Event) -> bool,
+    {
+        self.wait_map(|evt| predicate(&evt).then_some(evt), deadline)
+            .await
+    }
+
+    /// Wait for a window event whose container matches `criteria`, returning
+    /// the container. Needs a subscription to `Subscribe::Window`.
+    pub async fn wait_for_window(
+        &mut self,
+        criteria: &Criteria,
+        deadline: Instant,
+    ) -> io::Result<reply::Node> {
+        self.wait_map(
+            |evt| match evt {
+                event::Event::Window(w) if criteria.matches(&w.container) => Some(w.container),
+                _ => None,
+            },
+            deadline,
+        )
+        .await
+    }
+
+    /// Wait for the workspace called `name` to be focused, returning it. Needs
+    /// a subscription to `Subscribe::Workspace`.
+    pub async fn wait_for_workspace(
+        &mut self,
+        name: &str,
+        deadline: Instant,
+    ) -> io::Result<reply::Node> {
+        self.wait_map(
+            |evt| match evt {
+                event::Event::Workspace(w) if w.change == event::WorkspaceChange::Focus => {
+                    w.current.filter(|n| n.name.as_deref() == Some(name))
+                }
+                _ => None,
+            },
+            deadline,
+        )
+        .await
+    }
+
+    async fn wait_map<T, F>(&mut self, mut f: F, deadline: Instant) -> io::Result<T>
+    where
+        F: FnMut(event::Event) -> Option<T>,
+    {
+        let wait = async {
+            loop {
+                if let Some(found) = f(self.next().await?) {
+                    return Ok(found);
+                }
+            }
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        future::timeout(remaining, wait)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "deadline passed"))?
+    }
+}
 
-        let mut payload = vec![0_u8; payload_len];
-        self.inner.read_exact(&mut payload).await?;
+impl<S: Read + Unpin> Stream for EventStream<S> {
+    type Item = io::Result<event::Event>;
 
-        decode_event(msg_type, payload)
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let inner = &mut this.inner;
+        this.reader
+            .poll_next(|buf| Pin::new(&mut *inner).poll_read(cx, buf))
     }
 }
@@ -0,0 +1,50 @@
+use async_i3ipc::{event::Event, EventStream, MAGIC};
+use async_std::{future, io::WriteExt, os::unix::net::UnixStream, prelude::*};
+use std::time::Duration;
+
+fn event_frame(evt_type: u32, payload: &str) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend(MAGIC.as_bytes());
+    buf.extend((payload.len() as u32).to_ne_bytes());
+    buf.extend((evt_type | 1 << 31).to_ne_bytes());
+    buf.extend(payload.as_bytes());
+    buf
+}
+
+#[async_std::test]
+async fn test_next_is_cancel_safe() {
+    let (mut server, client) = UnixStream::pair().unwrap();
+    let mut events = EventStream::new(client);
+    let frame = event_frame(7, r#"{"first":false,"payload":"barrier"}"#);
+
+    // only half a frame arrives before the read is cancelled
+    server.write_all(&frame[..10]).await.unwrap();
+    let cancelled = future::timeout(Duration::from_millis(20), events.next()).await;
+    assert!(cancelled.is_err());
+
+    server.write_all(&frame[10..]).await.unwrap();
+    match events.next().await.unwrap() {
+        Event::Tick(tick) => assert_eq!(tick.payload, "barrier"),
+        other => panic!("expected tick, got {:?}", other),
+    }
+}
+
+#[async_std::test]
+async fn test_stream_ends_on_close() {
+    let (mut server, client) = UnixStream::pair().unwrap();
+    let mut events = EventStream::new(client);
+    server
+        .write_all(&event_frame(
+            2,
+            r#"{"change":"default","pango_markup":false}"#,
+        ))
+        .await
+        .unwrap();
+    drop(server);
+
+    assert!(matches!(
+        StreamExt::next(&mut events).await,
+        Some(Ok(Event::Mode(_)))
+    ));
+    assert!(StreamExt::next(&mut events).await.is_none());
+}
@@ -0,0 +1,14 @@
+use std::io;
+
+use futures_i3ipc::{reply, I3};
+
+fn main() -> io::Result<()> {
+    smol::block_on(async {
+        let mut i3 = I3::connect().await?;
+        // this type can be inferred, here is written explicitly:
+        let tree: reply::Node = i3.get_tree().await?;
+        println!("{:#?}", tree);
+
+        Ok(())
+    })
+}
@@ -0,0 +1,45 @@
+use async_io::Async;
+use futures_core::Stream;
+use futures_util::io::AsyncRead;
+use i3ipc_types::{event, frame};
+
+use std::{
+    io,
+    os::unix::net::UnixStream,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Stream of [Event](../event/enum.Event.html)s received after subscribing.
+/// Bytes are buffered internally, so dropping a pending `next()` (in a
+/// `select!` for instance) never loses part of a frame.
+#[derive(Debug)]
+pub struct EventStream<S = Async<UnixStream>> {
+    inner: S,
+    reader: frame::EventReader,
+}
+
+impl<S: AsyncRead + Unpin> EventStream<S> {
+    pub fn new(inner: S) -> Self {
+        Self::with_max_frame_len(inner, frame::DEFAULT_MAX_FRAME_LEN)
+    }
+
+    /// Like `new` but rejects events with payloads over `len` bytes
+    pub fn with_max_frame_len(inner: S, len: usize) -> Self {
+        Self {
+            inner,
+            reader: frame::EventReader::with_max_frame_len(len),
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> Stream for EventStream<S> {
+    type Item = io::Result<event::Event>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let inner = &mut this.inner;
+        this.reader
+            .poll_next(|buf| Pin::new(&mut *inner).poll_read(cx, buf))
+    }
+}
@@ -0,0 +1,68 @@
+use futures_i3ipc::{event::Event, EventStream, MAGIC};
+use futures_util::{io::AsyncRead, StreamExt};
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Hands out a few bytes at a time, returning `Pending` in between reads
+struct Trickle {
+    data: Vec<u8>,
+    pos: usize,
+    pending: bool,
+}
+
+impl AsyncRead for Trickle {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.pending {
+            self.pending = false;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        self.pending = true;
+        let n = 5.min(buf.len()).min(self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Poll::Ready(Ok(n))
+    }
+}
+
+fn event_frame(evt_type: u32, payload: &str) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend(MAGIC.as_bytes());
+    buf.extend((payload.len() as u32).to_ne_bytes());
+    buf.extend((evt_type | 1 << 31).to_ne_bytes());
+    buf.extend(payload.as_bytes());
+    buf
+}
+
+#[test]
+fn test_events_across_partial_reads() {
+    let mut data = event_frame(7, r#"{"first":true,"payload":""}"#);
+    data.extend(event_frame(
+        2,
+        r#"{"change":"resize","pango_markup":false}"#,
+    ));
+    let mut events = EventStream::new(Trickle {
+        data,
+        pos: 0,
+        pending: false,
+    });
+
+    smol::block_on(async {
+        match events.next().await {
+            Some(Ok(Event::Tick(tick))) => assert!(tick.first),
+            other => panic!("expected tick, got {:?}", other),
+        }
+        match events.next().await {
+            Some(Ok(Event::Mode(mode))) => assert_eq!(mode.change.as_str(), "resize"),
+            other => panic!("expected mode, got {:?}", other),
+        }
+        assert!(events.next().await.is_none());
+    });
+}
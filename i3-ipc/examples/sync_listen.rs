@@ -6,7 +6,7 @@ use i3_ipc::{
 use std::io;
 
 fn main() -> io::Result<()> {
-    let mut i3 = I3Stream::conn_sub(&[Subscribe::Window, Subscribe::Workspace])?;
+    let mut i3 = I3Stream::conn_sub([Subscribe::Window, Subscribe::Workspace])?;
     for e in i3.listen() {
         match e? {
             Event::Workspace(ev) => println!("workspace change event {:?}", ev),
@@ -10,7 +10,7 @@
 //! use std::io;
 //!
 //! fn main() -> io::Result<()> {
-//!     let mut i3 = I3Stream::conn_sub(&[Subscribe::Window, Subscribe::Workspace])?;
+//!     let mut i3 = I3Stream::conn_sub([Subscribe::Window, Subscribe::Workspace])?;
 //!     for e in i3.listen() {
 //!         match e? {
 //!             Event::Workspace(ev) => println!("workspace change event {:?}", ev),
@@ -48,29 +48,62 @@ use serde::de::DeserializeOwned;
 
 use std::{
     io::{self, Read, Write},
-    os::unix::net::UnixStream,
+    os::unix::{io::RawFd, net::UnixStream, prelude::FromRawFd},
+    path::Path,
+    time::Instant,
 };
 
 /// Our connection type, we implement `Connect` for this
 pub struct I3;
 
-/// `I3Stream` will hold the underlying UnixStream that communicates with i3
+/// `I3Stream` will hold the underlying stream that communicates with i3. This
+/// is a `UnixStream` by default, but any `Read + Write` transport can be used
+/// with [from_stream](struct.I3Stream.html#method.from_stream)
 #[derive(Debug)]
-pub struct I3Stream(UnixStream);
+pub struct I3Stream<S = UnixStream> {
+    stream: S,
+    /// Holds a frame cut short by a read timeout until the rest arrives
+    decoder: frame::Decoder,
+}
+
+impl<S: Read + Write> I3IPC for I3Stream<S> {
+    fn max_frame_len(&self) -> usize {
+        self.decoder.max_frame_len()
+    }
 
-impl I3IPC for I3Stream {}
-impl I3Protocol for I3Stream {}
+    fn decode_msg(&mut self) -> io::Result<(u32, Vec<u8>)> {
+        let mut chunk = [0; 4096];
+        loop {
+            if let Some(frame) = self.decoder.next_frame()? {
+                return Ok((frame.msg_type, frame.payload));
+            }
+            let want = self.decoder.missing()?.min(chunk.len());
+            match self.stream.read(&mut chunk[..want]) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.decoder.feed(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+impl<S> I3Protocol for I3Stream<S> {}
 
 /// Provides the `connect` method for `I3`
 impl Connect for I3 {
     type Stream = I3Stream;
 
     fn connect() -> io::Result<I3Stream> {
-        Ok(I3Stream(UnixStream::connect(socket_path()?)?))
+        Ok(I3Stream::from_stream(UnixStream::connect(socket_path()?)?))
     }
 }
 
 impl I3Stream {
+    /// Connect to the i3 (or sway) socket at `path`, skipping socket discovery
+    pub fn connect_to<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(I3Stream::from_stream(UnixStream::connect(path)?))
+    }
+
     /// Connect & subscribe in one method
     pub fn conn_sub<E>(events: E) -> io::Result<Self>
     where
@@ -81,26 +114,136 @@ impl I3Stream {
         Ok(i3)
     }
 
+    /// Wraps an inherited or socket-activated file descriptor
+    ///
+    /// # Safety
+    ///
+    /// `fd` must be an open unix stream socket connected to i3, and ownership
+    /// of it is transferred to the returned value.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        I3Stream::from_stream(UnixStream::from_raw_fd(fd))
+    }
+
+    /// Block until an event `predicate` returns true for arrives, or return an
+    /// `ErrorKind::TimedOut` error once `deadline` passes. Uses the socket's
+    /// read timeout, which is cleared again before returning. A frame the
+    /// timeout cuts short is kept and completed by the next read.
+    pub fn wait_for<F>(&mut self, mut predicate: F, deadline: Instant) -> io::Result<event::Event>
+    where
+        F: FnMut(&event::Event) -> bool,
+    {
+        self.wait_map(|evt| predicate(&evt).then_some(evt), deadline)
+    }
+
+    /// Wait for a window event whose container matches `criteria`, returning
+    /// the container. Needs a subscription to `Subscribe::Window`.
+    pub fn wait_for_window(
+        &mut self,
+        criteria: &criteria::Criteria,
+        deadline: Instant,
+    ) -> io::Result<reply::Node> {
+        self.wait_map(
+            |evt| match evt {
+                event::Event::Window(w) if criteria.matches(&w.container) => Some(w.container),
+                _ => None,
+            },
+            deadline,
+        )
+    }
+
+    /// Wait for the workspace called `name` to be focused, returning it. Needs
+    /// a subscription to `Subscribe::Workspace`.
+    pub fn wait_for_workspace(&mut self, name: &str, deadline: Instant) -> io::Result<reply::Node> {
+        self.wait_map(
+            |evt| match evt {
+                event::Event::Workspace(w) if w.change == event::WorkspaceChange::Focus => {
+                    w.current.filter(|n| n.name.as_deref() == Some(name))
+                }
+                _ => None,
+            },
+            deadline,
+        )
+    }
+
+    fn wait_map<T, F>(&mut self, mut f: F, deadline: Instant) -> io::Result<T>
+    where
+        F: FnMut(event::Event) -> Option<T>,
+    {
+        let res = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break Err(io::Error::new(io::ErrorKind::TimedOut, "deadline passed"));
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+            match self.receive_event() {
+                Ok(evt) => {
+                    if let Some(found) = f(evt) {
+                        break Ok(found);
+                    }
+                }
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    break Err(io::Error::new(io::ErrorKind::TimedOut, "deadline passed"))
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        self.stream.set_read_timeout(None)?;
+        res
+    }
+}
+
+impl<S: Read + Write> I3Stream<S> {
+    /// Use any `Read + Write` transport to talk to i3
+    pub fn from_stream(stream: S) -> Self {
+        I3Stream {
+            stream,
+            decoder: frame::Decoder::new(),
+        }
+    }
+
+    /// Consumes `I3Stream`, returning the underlying stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Reject replies and events with payloads over `len` bytes, defaults to
+    /// [DEFAULT_MAX_FRAME_LEN](frame/constant.DEFAULT_MAX_FRAME_LEN.html)
+    pub fn set_max_frame_len(&mut self, len: usize) {
+        self.decoder.set_max_frame_len(len);
+    }
+
+    /// Send any [Request](request/trait.Request.html) and read its reply. The
+    /// reply type is determined by the request, a reply to a different
+    /// message type is returned as an error.
+    pub fn request<R: request::Request>(&mut self, req: R) -> io::Result<R::Reply> {
+        let buf = req.encode()?;
+        self.write_all(&buf[..])?;
+        let (msg_type, payload) = self.decode_msg()?;
+        R::decode_reply(msg_type, &payload)
+    }
+
     /// sends a subscribe message to i3 with a json encoded array of types of
     /// events to listen to
     pub fn subscribe<E>(&mut self, events: E) -> io::Result<reply::Success>
     where
         E: AsRef<[event::Subscribe]>,
     {
-        let sub_json = serde_json::to_string(events.as_ref())?;
-        self.send_msg(msg::Msg::Subscribe, sub_json)?;
-        let resp: MsgResponse<reply::Success> = self.receive_msg()?;
-        Ok(resp.body)
+        self.request(request::Subscribe(events.as_ref().to_vec()))
     }
 
     /// Returns a type that implements `Iterator`, allowing us to listen to
     /// events
-    pub fn listen(&'_ mut self) -> I3Iter<'_> {
+    pub fn listen(&'_ mut self) -> I3Iter<'_, S> {
         I3Iter { stream: self }
     }
 
     /// same as `listen`
-    pub fn iter(&'_ mut self) -> I3Iter<'_> {
+    pub fn iter(&'_ mut self) -> I3Iter<'_, S> {
         I3Iter { stream: self }
     }
 
@@ -137,113 +280,132 @@ impl I3Stream {
     }
 
     /// Run an arbitrary command on i3.
-    pub fn run_command<S: AsRef<str>>(&mut self, payload: S) -> io::Result<Vec<reply::Success>> {
-        self.send_msg(msg::Msg::RunCommand, payload)?;
-        Ok(self.receive_msg()?.body)
+    pub fn run_command<P: AsRef<str>>(&mut self, payload: P) -> io::Result<Vec<reply::Success>> {
+        self.request(request::RunCommand(payload.as_ref().to_owned()))
+    }
+
+    /// Run several commands, pairing each with i3's reply to it. Normally the
+    /// commands are joined and sent as one message, which i3 runs to the end
+    /// whatever fails. With `stop_on_failure` they are sent one at a time
+    /// instead, and nothing after the first failure is run.
+    pub fn run_batch(
+        &mut self,
+        commands: Vec<command::Command>,
+        stop_on_failure: bool,
+    ) -> io::Result<Vec<command::CommandResult>> {
+        if !stop_on_failure {
+            let results = self.run_command(command::join(&commands)?)?;
+            return command::zip(commands, results);
+        }
+        let mut out = Vec::with_capacity(commands.len());
+        for cmd in commands {
+            let payload = command::join(std::slice::from_ref(&cmd))?;
+            let results = self.run_command(payload)?;
+            out.extend(command::zip(vec![cmd], results)?);
+            if out.last().is_some_and(|r| !r.is_success()) {
+                break;
+            }
+        }
+        Ok(out)
     }
 
     /// Get active workspaces
     pub fn get_workspaces(&mut self) -> io::Result<reply::Workspaces> {
-        let buf = self.encode_msg(msg::Msg::Workspaces);
-        self.write_all(&buf[..])?;
-        let resp: MsgResponse<Vec<reply::Workspace>> = self.receive_msg()?;
-        Ok(resp.body)
+        self.request(request::Workspaces)
     }
 
     /// Get active workspaces
     pub fn get_outputs(&mut self) -> io::Result<reply::Outputs> {
-        // self.send_msg(msg::Msg::Outputs, "")?;
-        let buf = self.encode_msg(msg::Msg::Outputs);
-        self.write_all(&buf[..])?;
-        Ok(self.receive_msg()?.body)
+        self.request(request::Outputs)
     }
 
     /// Get tree of all `Node`s in i3
     pub fn get_tree(&mut self) -> io::Result<reply::Node> {
-        // self.send_msg(msg::Msg::Tree, "")?;
-        let buf = self.encode_msg(msg::Msg::Tree);
-        self.write_all(&buf[..])?;
-        Ok(self.receive_msg()?.body)
+        self.request(request::Tree)
     }
 
     /// Get marks
     pub fn get_marks(&mut self) -> io::Result<reply::Marks> {
-        // self.send_msg(msg::Msg::Marks, "")?;
-        let buf = self.encode_msg(msg::Msg::Marks);
-        self.write_all(&buf[..])?;
-        Ok(self.receive_msg()?.body)
+        self.request(request::Marks)
     }
 
     /// Get your active bar ids
     pub fn get_bar_ids(&mut self) -> io::Result<reply::BarIds> {
-        // self.send_msg(msg::Msg::BarConfig, "")?;
-        let buf = self.encode_msg(msg::Msg::BarConfig);
-        self.write_all(&buf[..])?;
-        Ok(self.receive_msg()?.body)
+        self.request(request::BarIds)
     }
 
     /// Get bar config by id (`get_bar_ids`)
-    pub fn get_bar_config<S: AsRef<str>>(&mut self, bar_id: S) -> io::Result<reply::BarConfig> {
-        self.send_msg(msg::Msg::BarConfig, bar_id)?;
-        Ok(self.receive_msg()?.body)
+    pub fn get_bar_config<P: AsRef<str>>(&mut self, bar_id: P) -> io::Result<reply::BarConfig> {
+        self.request(request::BarConfig(bar_id.as_ref().to_owned()))
     }
 
     /// Get i3 version and config location
     pub fn get_version(&mut self) -> io::Result<reply::Version> {
-        let buf = self.encode_msg(msg::Msg::Version);
-        self.write_all(&buf[..])?;
-        Ok(self.receive_msg()?.body)
+        self.request(request::Version)
     }
 
     /// Get i3 binding modes
     pub fn get_binding_modes(&mut self) -> io::Result<reply::BindingModes> {
-        let buf = self.encode_msg(msg::Msg::BindingModes);
-        self.write_all(&buf[..])?;
-        Ok(self.receive_msg()?.body)
+        self.request(request::BindingModes)
     }
 
     /// Get i3 config
     pub fn get_config(&mut self) -> io::Result<reply::Config> {
-        let buf = self.encode_msg(msg::Msg::Config);
-        self.write_all(&buf[..])?;
-        Ok(self.receive_msg()?.body)
+        self.request(request::Config)
     }
 
     /// Convenience over `msg::Msg::Tick` and response
     pub fn get_tick(&mut self) -> io::Result<reply::Success> {
-        let buf = self.encode_msg(msg::Msg::Tick);
-        self.write_all(&buf[..])?;
-        Ok(self.receive_msg()?.body)
+        self.request(request::Tick::default())
+    }
+
+    /// Send a tick with `payload`, subscribers receive it as a `TickData`
+    pub fn send_tick<P: AsRef<str>>(&mut self, payload: P) -> io::Result<reply::Success> {
+        self.request(request::Tick(payload.as_ref().to_owned()))
+    }
+
+    /// Send a uniquely named tick and block until it comes back on `events`,
+    /// another connection subscribed to `Subscribe::Tick`. i3 delivers events
+    /// in order, so once this returns every event caused by commands sent
+    /// before it has been received. Those events are returned rather than
+    /// dropped. A tick i3 rejects is an `InvalidData` error.
+    pub fn barrier<T: Read + Write>(
+        &mut self,
+        events: &mut I3Stream<T>,
+    ) -> io::Result<Vec<event::Event>> {
+        let mut barrier = request::Barrier::new();
+        barrier.check(&self.request(barrier.tick())?)?;
+        loop {
+            if barrier.push(events.receive_event()?) {
+                return Ok(barrier.into_events());
+            }
+        }
     }
 
     /// Convenience over `msg::Msg::Sync` and response
     pub fn get_sync(&mut self) -> io::Result<reply::Success> {
-        let buf = self.encode_msg(msg::Msg::Sync);
-        self.write_all(&buf[..])?;
-        Ok(self.receive_msg()?.body)
+        self.request(request::Sync)
     }
 
     /// Get i3 binding state
     pub fn get_binding_state(&mut self) -> io::Result<reply::BindingState> {
-        let buf = self.encode_msg(msg::Msg::BindingState);
-        self.write_all(&buf[..])?;
-        Ok(self.receive_msg()?.body)
+        self.request(request::BindingState)
     }
 }
 
-impl Read for I3Stream {
+impl<S: Read> Read for I3Stream<S> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.0.read(buf)
+        self.stream.read(buf)
     }
 }
 
-impl Write for I3Stream {
+impl<S: Write> Write for I3Stream<S> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0.write(buf)
+        self.stream.write(buf)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.0.flush()
+        self.stream.flush()
     }
 }
 
@@ -251,11 +413,11 @@ impl Write for I3Stream {
 /// method). The iterator will advance each iteration on receiving an `Event`.
 /// These are decoded using `serde_json` and returned
 #[derive(Debug)]
-pub struct I3Iter<'a> {
-    stream: &'a mut I3Stream,
+pub struct I3Iter<'a, S = UnixStream> {
+    stream: &'a mut I3Stream<S>,
 }
 
-impl<'a> Iterator for I3Iter<'a> {
+impl<'a, S: Read + Write> Iterator for I3Iter<'a, S> {
     type Item = io::Result<event::Event>;
 
     fn next(&mut self) -> Option<Self::Item> {
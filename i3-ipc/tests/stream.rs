@@ -0,0 +1,119 @@
+use i3_ipc::{event::Event, msg::Msg, I3Stream, MAGIC};
+use std::{
+    io::{self, Cursor, Read, Write},
+    time::{Duration, Instant},
+};
+
+/// Replays canned replies and records everything written to it
+struct Replay {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+    /// Fail the next read with `Interrupted`, as a signal would
+    interrupt: bool,
+}
+
+impl Read for Replay {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if std::mem::take(&mut self.interrupt) {
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        self.input.read(buf)
+    }
+}
+
+impl Write for Replay {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn frame(msg_type: u32, payload: &str) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend(MAGIC.as_bytes());
+    buf.extend((payload.len() as u32).to_ne_bytes());
+    buf.extend(msg_type.to_ne_bytes());
+    buf.extend(payload.as_bytes());
+    buf
+}
+
+#[test]
+fn test_run_command_over_generic_stream() {
+    let mut i3 = I3Stream::from_stream(Replay {
+        input: Cursor::new(frame(0, r#"[{"success":true}]"#)),
+        output: Vec::new(),
+        interrupt: false,
+    });
+
+    let resp = i3.run_command("nop").unwrap();
+    assert!(resp[0].success);
+    assert_eq!(
+        i3.into_inner().output,
+        frame(u32::from(Msg::RunCommand), "nop")
+    );
+}
+
+#[test]
+fn test_wait_for_times_out() {
+    let (client, mut server) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut i3 = I3Stream::from_stream(client);
+    server
+        .write_all(&frame(7 | 1 << 31, r#"{"first":true,"payload":""}"#))
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_millis(20);
+    let evt = i3.wait_for(|_| true, deadline).unwrap();
+    assert!(matches!(evt, Event::Tick(t) if t.first));
+    let err = i3.wait_for(|_| true, deadline).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn test_wait_for_keeps_partial_frame() {
+    let (client, mut server) = std::os::unix::net::UnixStream::pair().unwrap();
+    let mut i3 = I3Stream::from_stream(client);
+    let buf = frame(7 | 1 << 31, r#"{"first":true,"payload":""}"#);
+    let (head, tail) = buf.split_at(20);
+    server.write_all(head).unwrap();
+
+    let deadline = Instant::now() + Duration::from_millis(20);
+    let err = i3.wait_for(|_| true, deadline).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+    server.write_all(tail).unwrap();
+    let evt = i3.receive_event().unwrap();
+    assert!(matches!(evt, Event::Tick(t) if t.first));
+}
+
+#[test]
+fn test_run_batch_stops_on_failure() {
+    let mut input = frame(0, r#"[{"success":true}]"#);
+    input.extend(frame(0, r#"[{"success":false,"error":"No such mode"}]"#));
+    let mut i3 = I3Stream::from_stream(Replay {
+        input: Cursor::new(input),
+        output: Vec::new(),
+        interrupt: false,
+    });
+
+    let cmds = vec!["nop".into(), "mode bogus".into(), "kill".into()];
+    let out = i3.run_batch(cmds, true).unwrap();
+    assert_eq!(out.len(), 2);
+    assert!(out[0].is_success());
+    assert_eq!(out[1].command.action, "mode bogus");
+    assert_eq!(out[1].error(), Some("No such mode"));
+}
+
+#[test]
+fn test_read_retries_after_interrupt() {
+    let mut i3 = I3Stream::from_stream(Replay {
+        input: Cursor::new(frame(0, r#"[{"success":true}]"#)),
+        output: Vec::new(),
+        interrupt: true,
+    });
+
+    let resp = i3.run_command("nop").unwrap();
+    assert!(resp[0].success);
+}
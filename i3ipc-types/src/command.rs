@@ -0,0 +1,210 @@
+//! Commands for batching into a single `RunCommand` message. i3 replies with
+//! one [Success](../reply/struct.Success.html) per command, in order, so the
+//! results of a batch can be matched back up with the commands that produced
+//! them.
+use serde::{Deserialize, Serialize};
+
+use std::{fmt, io};
+
+use crate::{criteria::Criteria, reply};
+
+/// A single i3 command, optionally restricted by criteria
+#[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Clone)]
+pub struct Command {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub criteria: Option<Criteria>,
+    /// The command itself, e.g. `move container to workspace 3`
+    pub action: String,
+}
+
+impl Command {
+    pub fn new<S: Into<String>>(action: S) -> Self {
+        Command {
+            criteria: None,
+            action: action.into(),
+        }
+    }
+
+    /// Run `action` only on windows matching `criteria`
+    pub fn with_criteria<S: Into<String>>(criteria: Criteria, action: S) -> Self {
+        Command {
+            criteria: Some(criteria),
+            action: action.into(),
+        }
+    }
+}
+
+impl From<&str> for Command {
+    fn from(action: &str) -> Self {
+        Command::new(action)
+    }
+}
+
+impl From<String> for Command {
+    fn from(action: String) -> Self {
+        Command::new(action)
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.criteria {
+            Some(criteria) => write!(f, "{} {}", criteria, self.action),
+            None => f.write_str(&self.action),
+        }
+    }
+}
+
+/// A command from a batch along with i3's reply to it
+#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+pub struct CommandResult {
+    pub command: Command,
+    /// `None` if i3 never ran the command, because one before it in the
+    /// batch failed to parse
+    pub result: Option<reply::Success>,
+}
+
+impl CommandResult {
+    pub fn is_success(&self) -> bool {
+        self.result.as_ref().is_some_and(|r| r.success)
+    }
+
+    /// Why the command failed, `None` if it succeeded
+    pub fn error(&self) -> Option<&str> {
+        match &self.result {
+            Some(r) if r.success => None,
+            Some(r) => Some(r.error.as_deref().unwrap_or("command failed")),
+            None => Some("not run, an earlier command failed to parse"),
+        }
+    }
+}
+
+/// Join `commands` with `;` into one payload. A command containing a `;` or
+/// `,` outside of quotes would be split by i3 into several, so results could
+/// no longer be matched up; such commands are rejected with `InvalidInput`.
+pub fn join(commands: &[Command]) -> io::Result<String> {
+    let mut out = Vec::with_capacity(commands.len());
+    for cmd in commands {
+        if has_separator(&cmd.action) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("command {:?} contains an unquoted ';' or ','", cmd.action),
+            ));
+        }
+        out.push(cmd.to_string());
+    }
+    Ok(out.join("; "))
+}
+
+/// Pair each command with its reply. i3 stops at the first command it can't
+/// parse, replying to it with `parse_error` set and not at all to the rest,
+/// which are left without a result. More replies than commands is an
+/// `InvalidData` error.
+pub fn zip(commands: Vec<Command>, results: Vec<reply::Success>) -> io::Result<Vec<CommandResult>> {
+    if commands.len() < results.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "sent {} commands but i3 replied with {} results",
+                commands.len(),
+                results.len()
+            ),
+        ));
+    }
+    let mut results = results.into_iter();
+    Ok(commands
+        .into_iter()
+        .map(|command| CommandResult {
+            command,
+            result: results.next(),
+        })
+        .collect())
+}
+
+/// Quote a command argument, escaping `"` and `\`
+pub fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Switch to the workspace called `name`, creating it if needed. Switching
+/// to the current workspace stays there even with
+/// `workspace_auto_back_and_forth` set.
+pub fn focus_workspace(name: &str) -> Command {
+    Command::new(format!(
+        "workspace --no-auto-back-and-forth {}",
+        quote(name)
+    ))
+}
+
+fn has_separator(action: &str) -> bool {
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in action.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => quoted = !quoted,
+            ';' | ',' if !quoted => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join() {
+        let cmds = vec![
+            Command::from("workspace 2"),
+            Command::with_criteria(Criteria::con_id(5), "move container to workspace \"a;b\""),
+        ];
+        assert_eq!(
+            join(&cmds).unwrap(),
+            r#"workspace 2; [con_id=5] move container to workspace "a;b""#
+        );
+        assert!(join(&[Command::from("focus left, kill")]).is_err());
+        assert!(join(&[Command::from(r#"rename workspace to "\";""#)]).is_ok());
+    }
+
+    #[test]
+    fn test_zip() {
+        let results: Vec<reply::Success> = serde_json::from_str(
+            r#"[{"success":true},{"success":false,"parse_error":true,"error":"Expected one of these tokens"}]"#,
+        )
+        .unwrap();
+        let cmds = vec![Command::from("nop"), Command::from("bogus")];
+        let out = zip(cmds.clone(), results.clone()).unwrap();
+        assert!(out[0].is_success());
+        assert_eq!(out[1].command.action, "bogus");
+        assert!(out[1].result.as_ref().unwrap().parse_error);
+        assert!(zip(cmds[..1].to_vec(), results).is_err());
+    }
+
+    #[test]
+    fn test_zip_parse_error() {
+        let results: Vec<reply::Success> = serde_json::from_str(
+            r#"[{"success":true},{"success":false,"parse_error":true,"error":"Expected one of these tokens"}]"#,
+        )
+        .unwrap();
+        let cmds = vec![
+            Command::from("nop"),
+            Command::from("bogus"),
+            Command::from("focus left"),
+            Command::from("kill"),
+        ];
+        let out = zip(cmds, results).unwrap();
+        assert_eq!(out.len(), 4);
+        assert!(out[0].is_success());
+        assert_eq!(out[0].error(), None);
+        assert_eq!(out[1].command.action, "bogus");
+        assert_eq!(out[1].error(), Some("Expected one of these tokens"));
+        for r in &out[2..] {
+            assert!(!r.is_success());
+            assert_eq!(r.result, None);
+        }
+        assert_eq!(out[3].command.action, "kill");
+    }
+}
@@ -0,0 +1,187 @@
+//! Client-side window matching, mirroring a subset of i3's
+//! [command criteria](https://i3wm.org/docs/userguide.html#command_criteria).
+//! Unlike i3, values are compared exactly rather than as regular expressions.
+//! Every field that is set must match; an empty `Criteria` matches any
+//! container.
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+use crate::reply::{Node, WindowType};
+
+#[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Criteria {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_type: Option<WindowType>,
+    #[cfg(feature = "sway")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub app_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub con_id: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub con_mark: Option<String>,
+}
+
+impl Criteria {
+    /// Criteria matching a single container by id
+    pub fn con_id(id: usize) -> Self {
+        Criteria {
+            con_id: Some(id),
+            ..Criteria::default()
+        }
+    }
+
+    /// Criteria matching windows by class
+    pub fn class<S: Into<String>>(class: S) -> Self {
+        Criteria {
+            class: Some(class.into()),
+            ..Criteria::default()
+        }
+    }
+
+    /// True if `node` satisfies every criterion set. This doesn't check that
+    /// `node` is a window: a container without window properties matches
+    /// criteria that only set `con_id` or `con_mark`.
+    pub fn matches(&self, node: &Node) -> bool {
+        fn eq(want: &Option<String>, have: Option<&String>) -> bool {
+            want.as_ref().is_none_or(|w| have == Some(w))
+        }
+
+        if self.con_id.is_some_and(|id| id != node.id) {
+            return false;
+        }
+        if let Some(mark) = &self.con_mark {
+            if !node.marks.as_ref().is_some_and(|m| m.0.contains(mark)) {
+                return false;
+            }
+        }
+        if let Some(window_type) = &self.window_type {
+            if node.window_type.as_ref() != Some(window_type) {
+                return false;
+            }
+        }
+        #[cfg(feature = "sway")]
+        if !eq(&self.app_id, node.app_id.as_ref()) {
+            return false;
+        }
+        let props = node.window_properties.as_ref();
+        eq(&self.class, props.and_then(|p| p.class.as_ref()))
+            && eq(&self.instance, props.and_then(|p| p.instance.as_ref()))
+            && eq(&self.title, props.and_then(|p| p.title.as_ref()))
+            && eq(
+                &self.window_role,
+                props.and_then(|p| p.window_role.as_ref()),
+            )
+    }
+}
+
+/// Formats as i3 command criteria, e.g. `[class="^Firefox$" con_mark="^web$"]`.
+/// Values are anchored and escaped, so i3 matches them exactly too.
+impl fmt::Display for Criteria {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        let mut exact = |key: &str, value: &Option<String>| {
+            if let Some(v) = value {
+                parts.push(format!("{key}=\"^{}$\"", escape(v)));
+            }
+        };
+        exact("class", &self.class);
+        exact("instance", &self.instance);
+        exact("title", &self.title);
+        exact("window_role", &self.window_role);
+        #[cfg(feature = "sway")]
+        exact("app_id", &self.app_id);
+        exact("con_mark", &self.con_mark);
+        if let Some(t) = &self.window_type {
+            parts.push(format!("window_type=\"{t}\""));
+        }
+        if let Some(id) = self.con_id {
+            parts.push(format!("con_id={id}"));
+        }
+        write!(f, "[{}]", parts.join(" "))
+    }
+}
+
+/// Escape `s` for a quoted criteria value in a command, e.g.
+/// `[title="^...$"]`, so i3 matches it literally. i3's command parser turns
+/// `\\` and `\"` back into `\` and `"` before compiling the regex, so the
+/// backslashes [escape_regex](fn.escape_regex.html) adds are doubled again.
+pub fn escape(s: &str) -> String {
+    escape_regex(s).replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Escape regex metacharacters so `s` matches literally where i3 reads the
+/// regex as is, e.g. the `swallows` of a layout file
+pub fn escape_regex(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(
+            c,
+            '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(class: &str, id: usize) -> Node {
+        let tree = include_str!("../test/tree.json");
+        let mut node: Node = serde_json::from_str(tree).unwrap();
+        node.id = id;
+        node.window_properties = serde_json::from_value(serde_json::json!({
+            "class": class,
+            "instance": "navigator",
+            "title": "Mozilla Firefox",
+        }))
+        .unwrap();
+        node
+    }
+
+    #[test]
+    fn test_matches() {
+        let node = window("Firefox", 7);
+        assert!(Criteria::default().matches(&node));
+        assert!(Criteria::class("Firefox").matches(&node));
+        assert!(!Criteria::class("firefox").matches(&node));
+        assert!(Criteria::con_id(7).matches(&node));
+        assert!(!Criteria::con_id(8).matches(&node));
+        let c = Criteria {
+            instance: Some("navigator".into()),
+            window_role: Some("browser".into()),
+            ..Criteria::default()
+        };
+        assert!(!c.matches(&node));
+    }
+
+    #[test]
+    fn test_display() {
+        let c = Criteria {
+            class: Some("Gimp-2.10".into()),
+            con_id: Some(3),
+            ..Criteria::default()
+        };
+        assert_eq!(c.to_string(), r#"[class="^Gimp-2\\.10$" con_id=3]"#);
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape_regex(r#"a\b.c"d"#), r#"a\\b\.c"d"#);
+        // i3 unescapes `\\` and `\"` in a quoted command argument first
+        assert_eq!(escape(r#"a\b.c"d"#), r#"a\\\\b\\.c\"d"#);
+    }
+}
@@ -1,11 +1,13 @@
 //! For subscribing and receiving events, each struct matches a particular
 //! `Subscribe` variant. For instance, subscribing with `Subscribe::Workspace`
 //! will net `Event::Workspace` when workspace events are sent over the ipc.
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use std::{fmt, io, ops};
 
 use crate::reply;
 
-#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+#[derive(Deserialize, Serialize, PartialEq, Eq, Hash, Debug, Clone, Copy)]
 #[serde(rename_all = "snake_case")]
 pub enum Subscribe {
     Workspace,
@@ -19,9 +21,25 @@ pub enum Subscribe {
     Tick,
 }
 
-impl From<u32> for Subscribe {
-    fn from(num: u32) -> Self {
-        match num {
+impl Subscribe {
+    /// Every event type, for subscribing to everything
+    pub const ALL: [Subscribe; 8] = [
+        Subscribe::Workspace,
+        Subscribe::Output,
+        Subscribe::Mode,
+        Subscribe::Window,
+        Subscribe::BarConfigUpdate,
+        Subscribe::Binding,
+        Subscribe::Shutdown,
+        Subscribe::Tick,
+    ];
+}
+
+impl TryFrom<u32> for Subscribe {
+    type Error = io::Error;
+
+    fn try_from(num: u32) -> Result<Self, Self::Error> {
+        Ok(match num {
             0 => Subscribe::Workspace,
             1 => Subscribe::Output,
             2 => Subscribe::Mode,
@@ -30,8 +48,13 @@ impl From<u32> for Subscribe {
             5 => Subscribe::Binding,
             6 => Subscribe::Shutdown,
             7 => Subscribe::Tick,
-            _ => panic!("Unknown event found"),
-        }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown event found: {num}"),
+                ))
+            }
+        })
     }
 }
 
@@ -56,23 +79,23 @@ pub enum Event {
     Output(OutputData),
     Mode(ModeData),
     Window(Box<WindowData>),
-    BarConfig(BarConfigData),
+    BarConfig(Box<BarConfigData>),
     Binding(BindingData),
     Shutdown(ShutdownData),
     Tick(TickData),
 }
 
-#[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Copy, Clone)]
-#[serde(rename_all = "lowercase")]
-pub enum WorkspaceChange {
-    Focus,
-    Init,
-    Empty,
-    Urgent,
-    Rename,
-    Reload,
-    Restored,
-    Move,
+string_enum! {
+    pub enum WorkspaceChange {
+        Focus = "focus",
+        Init = "init",
+        Empty = "empty",
+        Urgent = "urgent",
+        Rename = "rename",
+        Reload = "reload",
+        Restored = "restored",
+        Move = "move",
+    }
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
@@ -84,66 +107,263 @@ pub struct WorkspaceData {
 
 #[derive(Deserialize, Serialize, Eq, Hash, PartialEq, Debug, Clone)]
 pub struct OutputData {
-    pub change: String,
+    pub change: OutputChange,
+}
+
+string_enum! {
+    pub enum OutputChange {
+        /// i3 doesn't say what changed, re-query outputs to find out
+        Unspecified = "unspecified",
+    }
 }
 
 #[derive(Deserialize, Serialize, Eq, Hash, PartialEq, Debug, Clone)]
 pub struct ModeData {
-    pub change: String,
+    pub change: ModeChange,
     pub pango_markup: bool,
 }
 
+/// The binding mode that was switched to
+#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+pub enum ModeChange {
+    Default,
+    /// A mode declared with `mode "name" { ... }` in the config
+    Named(String),
+}
+
+impl ModeChange {
+    pub fn as_str(&self) -> &str {
+        match self {
+            ModeChange::Default => "default",
+            ModeChange::Named(name) => name,
+        }
+    }
+}
+
+impl From<&str> for ModeChange {
+    fn from(s: &str) -> Self {
+        match s {
+            "default" => ModeChange::Default,
+            name => ModeChange::Named(name.to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for ModeChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for ModeChange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for ModeChange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(String::deserialize(deserializer)?.as_str().into())
+    }
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone)]
 pub struct WindowData {
     pub change: WindowChange,
     pub container: reply::Node,
 }
 
-#[derive(Deserialize, Serialize, Eq, Hash, PartialEq, Debug, Clone, Copy)]
-#[serde(rename_all = "snake_case")]
-pub enum WindowChange {
-    ///  the window has become managed by i3  
-    New,
-    /// the window has closed
-    Close,
-    /// the window has received input focus
-    Focus,
-    /// the window’s title has changed
-    Title,
-    /// the window has entered or exited fullscreen mode
-    FullscreenMode,
-    /// the window has changed its position in the tree
-    Move,
-    /// the window has transitioned to or from floating
-    Floating,
-    /// the window has become urgent or lost its urgent status
-    Urgent,
-    /// a mark has been added to or removed from the window
-    Mark,
+string_enum! {
+    pub enum WindowChange {
+        ///  the window has become managed by i3
+        New = "new",
+        /// the window has closed
+        Close = "close",
+        /// the window has received input focus
+        Focus = "focus",
+        /// the window’s title has changed
+        Title = "title",
+        /// the window has entered or exited fullscreen mode
+        FullscreenMode = "fullscreen_mode",
+        /// the window has changed its position in the tree
+        Move = "move",
+        /// the window has transitioned to or from floating
+        Floating = "floating",
+        /// the window has become urgent or lost its urgent status
+        Urgent = "urgent",
+        /// a mark has been added to or removed from the window
+        Mark = "mark",
+    }
 }
 
 pub type BarConfigData = reply::BarConfig;
 
 #[derive(Deserialize, Serialize, Eq, Hash, PartialEq, Debug, Clone)]
 pub struct BindingData {
-    pub change: String,
+    pub change: BindingChange,
     pub binding: BindingObject,
 }
 
+string_enum! {
+    pub enum BindingChange {
+        /// the binding was triggered and its command run
+        Run = "run",
+    }
+}
+
 #[derive(Deserialize, Serialize, Eq, Hash, PartialEq, Debug, Clone)]
 pub struct BindingObject {
     pub command: String,
-    pub event_state_mask: Vec<String>,
-    pub input_code: isize,
-    pub symbol: Option<String>,
+    pub event_state_mask: Modifiers,
+    /// Keycode for `bindcode` bindings, button number for mouse bindings and
+    /// 0 for bindings made with `bindsym`
+    pub input_code: u32,
+    pub symbol: Option<Keysym>,
     pub input_type: BindType,
 }
 
-#[derive(Deserialize, Serialize, Eq, Hash, PartialEq, Debug, Clone, Copy)]
-#[serde(rename_all = "lowercase")]
-pub enum BindType {
-    Keyboard,
-    Mouse,
+/// Modifier keys held down for a binding, parsed from i3's `event_state_mask`.
+/// The low byte matches X11's modifier masks; XKB groups use i3's own bits,
+/// 16 to 19.
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy, Default)]
+pub struct Modifiers(u32);
+
+impl Modifiers {
+    pub const SHIFT: Modifiers = Modifiers(1 << 0);
+    pub const LOCK: Modifiers = Modifiers(1 << 1);
+    pub const CTRL: Modifiers = Modifiers(1 << 2);
+    pub const MOD1: Modifiers = Modifiers(1 << 3);
+    pub const MOD2: Modifiers = Modifiers(1 << 4);
+    pub const MOD3: Modifiers = Modifiers(1 << 5);
+    pub const MOD4: Modifiers = Modifiers(1 << 6);
+    pub const MOD5: Modifiers = Modifiers(1 << 7);
+    pub const GROUP1: Modifiers = Modifiers(1 << 16);
+    pub const GROUP2: Modifiers = Modifiers(1 << 17);
+    pub const GROUP3: Modifiers = Modifiers(1 << 18);
+    pub const GROUP4: Modifiers = Modifiers(1 << 19);
+
+    const NAMES: [(Modifiers, &'static str); 12] = [
+        (Modifiers::SHIFT, "shift"),
+        (Modifiers::LOCK, "lock"),
+        (Modifiers::CTRL, "ctrl"),
+        (Modifiers::MOD1, "Mod1"),
+        (Modifiers::MOD2, "Mod2"),
+        (Modifiers::MOD3, "Mod3"),
+        (Modifiers::MOD4, "Mod4"),
+        (Modifiers::MOD5, "Mod5"),
+        (Modifiers::GROUP1, "Group1"),
+        (Modifiers::GROUP2, "Group2"),
+        (Modifiers::GROUP3, "Group3"),
+        (Modifiers::GROUP4, "Group4"),
+    ];
+
+    pub const fn empty() -> Self {
+        Modifiers(0)
+    }
+
+    /// Build from a modifier mask as i3 writes it, dropping bits that aren't
+    /// modifiers or groups
+    pub const fn from_bits_truncate(bits: u32) -> Self {
+        Modifiers(bits & 0xf_00ff)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// True if every modifier in `other` is also set in `self`
+    pub const fn contains(&self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Look up a single modifier by the name i3 uses for it
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(m, _)| *m)
+    }
+
+    /// Names of the modifiers set, in i3's order
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        Self::NAMES
+            .iter()
+            .filter(move |(m, _)| self.contains(*m))
+            .map(|(_, n)| *n)
+    }
+}
+
+impl ops::BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Modifiers(self.0 | rhs.0)
+    }
+}
+
+impl ops::BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl ops::BitAnd for Modifiers {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Modifiers(self.0 & rhs.0)
+    }
+}
+
+impl Serialize for Modifiers {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.names())
+    }
+}
+
+impl<'de> Deserialize<'de> for Modifiers {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // names unknown to us are dropped
+        Ok(Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .filter_map(|name| Modifiers::from_name(name))
+            .fold(Modifiers::empty(), |acc, m| acc | m))
+    }
+}
+
+/// An X11 keysym name as written in `bindsym`, e.g. `Return` or
+/// `XF86AudioMute`
+#[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Clone)]
+#[serde(transparent)]
+pub struct Keysym(pub String);
+
+impl Keysym {
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Keysym {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl PartialEq<str> for Keysym {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+string_enum! {
+    pub enum BindType {
+        Keyboard = "keyboard",
+        Mouse = "mouse",
+    }
 }
 
 #[derive(Deserialize, Serialize, Eq, Hash, PartialEq, Debug, Clone)]
@@ -151,11 +371,11 @@ pub struct ShutdownData {
     pub change: ShutdownChange,
 }
 
-#[derive(Deserialize, Serialize, Eq, Hash, PartialEq, Debug, Clone, Copy)]
-#[serde(rename_all = "lowercase")]
-pub enum ShutdownChange {
-    Restart,
-    Exit,
+string_enum! {
+    pub enum ShutdownChange {
+        Restart = "restart",
+        Exit = "exit",
+    }
 }
 
 #[derive(Deserialize, Serialize, Eq, Hash, PartialEq, Debug, Clone)]
@@ -163,3 +383,45 @@ pub struct TickData {
     pub first: bool,
     pub payload: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binding_event() {
+        let output = r#"{"change":"run","binding":{"command":"nop","event_state_mask":["shift","Mod4","Mod9"],"input_code":0,"symbol":"Return","input_type":"keyboard"}}"#;
+        let b: BindingData = serde_json::from_str(output).unwrap();
+        assert_eq!(b.change, BindingChange::Run);
+        assert_eq!(
+            b.binding.event_state_mask,
+            Modifiers::SHIFT | Modifiers::MOD4
+        );
+        assert!(b.binding.event_state_mask.contains(Modifiers::MOD4));
+        assert!(!b.binding.event_state_mask.contains(Modifiers::CTRL));
+        assert_eq!(b.binding.symbol.as_ref().unwrap(), "Return");
+        assert_eq!(
+            serde_json::to_value(b.binding.event_state_mask).unwrap(),
+            serde_json::json!(["shift", "Mod4"])
+        );
+    }
+
+    #[test]
+    fn test_modifier_groups() {
+        // i3 keeps XKB groups above the X11 modifiers
+        let m = Modifiers::from_bits_truncate(1 << 6 | 1 << 8 | 1 << 17);
+        assert_eq!(m, Modifiers::MOD4 | Modifiers::GROUP2);
+        assert_eq!(m.names().collect::<Vec<_>>(), ["Mod4", "Group2"]);
+        assert_eq!(m.bits(), 1 << 6 | 1 << 17);
+    }
+
+    #[test]
+    fn test_mode_event() {
+        let m: ModeData =
+            serde_json::from_str(r#"{"change":"default","pango_markup":false}"#).unwrap();
+        assert_eq!(m.change, ModeChange::Default);
+        let m: ModeData =
+            serde_json::from_str(r#"{"change":"resize","pango_markup":true}"#).unwrap();
+        assert_eq!(m.change, ModeChange::Named("resize".into()));
+    }
+}
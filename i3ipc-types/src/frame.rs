@@ -0,0 +1,425 @@
+//! Runtime independent encoding and decoding of i3's IPC frames. Nothing in
+//! here performs IO, clients feed it bytes from whatever transport they use.
+//!
+//! Every message to and from i3 looks like:
+//!
+//! ```text
+//! "i3-ipc" <payload len: u32> <msg type: u32> <payload>
+//! ```
+//!
+//! where both integers are in native byte order. Clients that can read an
+//! exact number of bytes use [Header](struct.Header.html) directly, clients
+//! that receive bytes in arbitrary chunks use [decode](fn.decode.html) or the
+//! buffering [Decoder](struct.Decoder.html).
+//!
+//! Payload lengths are checked against a maximum before anything is
+//! allocated, so a corrupt or hostile header can't trigger a 4 GiB
+//! allocation. None of the functions in here panic on malformed input,
+//! [decode_events](fn.decode_events.html) is a convenient fuzzing target:
+//!
+//! ```ignore
+//! fuzz_target!(|data: &[u8]| {
+//!     let _ = i3ipc_types::frame::decode_events(data, DEFAULT_MAX_FRAME_LEN);
+//! });
+//! ```
+use std::{io, task::Poll};
+
+use crate::{decode_event, event, msg, MAGIC};
+
+/// Length of the magic string plus the two `u32`s that precede every payload
+pub const HEADER_LEN: usize = 14;
+
+/// Default limit for the payload length of a single frame. The tree of a large
+/// session is a few MiB, this leaves plenty of headroom.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// The fixed size part of a frame
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct Header {
+    pub len: u32,
+    pub msg_type: u32,
+}
+
+impl Header {
+    /// Parse a header, returning an error if the magic string doesn't match or
+    /// the payload is larger than
+    /// [DEFAULT_MAX_FRAME_LEN](constant.DEFAULT_MAX_FRAME_LEN.html)
+    pub fn parse(buf: &[u8; HEADER_LEN]) -> io::Result<Self> {
+        Self::parse_with_limit(buf, DEFAULT_MAX_FRAME_LEN)
+    }
+
+    /// Like `parse` but with a custom limit for the payload length
+    pub fn parse_with_limit(buf: &[u8; HEADER_LEN], max_len: usize) -> io::Result<Self> {
+        if &buf[0..6] != MAGIC.as_bytes() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Expected 'i3-ipc' but received: {:?}", &buf[0..6]),
+            ));
+        }
+        let len = u32::from_ne_bytes([buf[6], buf[7], buf[8], buf[9]]);
+        if len as usize > max_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Frame payload of {len} bytes exceeds the limit of {max_len} bytes"),
+            ));
+        }
+        Ok(Header {
+            len,
+            msg_type: u32::from_ne_bytes([buf[10], buf[11], buf[12], buf[13]]),
+        })
+    }
+
+    /// Total length of the frame this header starts
+    pub fn frame_len(&self) -> usize {
+        HEADER_LEN + self.len as usize
+    }
+}
+
+/// A complete message or event, payload not yet deserialized
+#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+pub struct Frame {
+    pub msg_type: u32,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Events are distinguished from replies by the highest bit of the type
+    pub fn is_event(&self) -> bool {
+        self.msg_type & (1 << 31) != 0
+    }
+}
+
+/// Encode a message and optional payload into a frame ready to be written
+pub fn encode(msg: msg::Msg, payload: Option<&[u8]>) -> Vec<u8> {
+    let payload = payload.unwrap_or_default();
+    let mut buf = Vec::with_capacity(HEADER_LEN + payload.len());
+    buf.extend(MAGIC.as_bytes());
+    buf.extend((payload.len() as u32).to_ne_bytes());
+    buf.extend(<u32 as From<msg::Msg>>::from(msg).to_ne_bytes());
+    buf.extend(payload);
+    buf
+}
+
+/// Try to decode a single frame from the start of `src`. Returns the frame
+/// and the number of bytes it occupied, or `None` if `src` doesn't hold a
+/// complete frame yet.
+pub fn decode(src: &[u8]) -> io::Result<Option<(Frame, usize)>> {
+    decode_with_limit(src, DEFAULT_MAX_FRAME_LEN)
+}
+
+/// Like `decode` but with a custom limit for the payload length
+pub fn decode_with_limit(src: &[u8], max_len: usize) -> io::Result<Option<(Frame, usize)>> {
+    let header = match src.get(..HEADER_LEN).and_then(|h| h.try_into().ok()) {
+        Some(h) => Header::parse_with_limit(h, max_len)?,
+        None => return Ok(None),
+    };
+    let end = header.frame_len();
+    match src.get(HEADER_LEN..end) {
+        Some(payload) => Ok(Some((
+            Frame {
+                msg_type: header.msg_type,
+                payload: payload.to_vec(),
+            },
+            end,
+        ))),
+        None => Ok(None),
+    }
+}
+
+/// Decode every frame in `data` as an event, stopping at the first error.
+/// Trailing bytes that don't form a complete frame are an `UnexpectedEof`
+/// error.
+pub fn decode_events(data: &[u8], max_len: usize) -> io::Result<Vec<event::Event>> {
+    let mut events = Vec::new();
+    let mut rest = data;
+    while let Some((frame, len)) = decode_with_limit(rest, max_len)? {
+        events.push(decode_event(frame.msg_type, frame.payload)?);
+        rest = &rest[len..];
+    }
+    if !rest.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "incomplete frame at end of input",
+        ));
+    }
+    Ok(events)
+}
+
+/// Buffers bytes as they arrive and yields frames once they are complete
+#[derive(Debug)]
+pub struct Decoder {
+    buf: Vec<u8>,
+    max_frame_len: usize,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::with_max_frame_len(DEFAULT_MAX_FRAME_LEN)
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a decoder that rejects frames with payloads over `max_len` bytes
+    pub fn with_max_frame_len(max_len: usize) -> Self {
+        Decoder {
+            buf: Vec::new(),
+            max_frame_len: max_len,
+        }
+    }
+
+    /// Append bytes read from the transport
+    pub fn feed(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Yield the next complete frame, if there is one
+    pub fn next_frame(&mut self) -> io::Result<Option<Frame>> {
+        match decode_with_limit(&self.buf, self.max_frame_len)? {
+            Some((frame, len)) => {
+                self.buf.drain(..len);
+                Ok(Some(frame))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Number of bytes buffered but not yet yielded as a frame
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Number of bytes still needed to complete the next frame, or its
+    /// header if that isn't complete yet. Reading no more than this never
+    /// buffers bytes past the end of a frame.
+    pub fn missing(&self) -> io::Result<usize> {
+        match self.buf.get(..HEADER_LEN).and_then(|h| h.try_into().ok()) {
+            Some(h) => {
+                let header = Header::parse_with_limit(h, self.max_frame_len)?;
+                Ok(header.frame_len().saturating_sub(self.buf.len()))
+            }
+            None => Ok(HEADER_LEN - self.buf.len()),
+        }
+    }
+
+    /// The largest payload this decoder accepts, in bytes
+    pub fn max_frame_len(&self) -> usize {
+        self.max_frame_len
+    }
+
+    /// Reject frames with payloads over `max_len` bytes from now on
+    pub fn set_max_frame_len(&mut self, max_len: usize) {
+        self.max_frame_len = max_len;
+    }
+}
+
+/// The runtime independent half of an async event stream: a
+/// [Decoder](struct.Decoder.html) and a read buffer. The stream's `poll_next`
+/// hands [poll_next](#method.poll_next) its transport's `poll_read`.
+#[derive(Debug)]
+pub struct EventReader {
+    decoder: Decoder,
+    buf: Box<[u8]>,
+    done: bool,
+}
+
+impl EventReader {
+    /// Reject events with payloads over `max_len` bytes
+    pub fn with_max_frame_len(max_len: usize) -> Self {
+        EventReader {
+            decoder: Decoder::with_max_frame_len(max_len),
+            buf: vec![0_u8; 4096].into_boxed_slice(),
+            done: false,
+        }
+    }
+
+    /// Yield the next event, reading with `poll_read` until one is complete.
+    /// The stream ends when the transport does, with an `UnexpectedEof` error
+    /// first if that cuts a frame short, and after a framing error.
+    pub fn poll_next<F>(&mut self, mut poll_read: F) -> Poll<Option<io::Result<event::Event>>>
+    where
+        F: FnMut(&mut [u8]) -> Poll<io::Result<usize>>,
+    {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            match self.decoder.next_frame() {
+                Ok(Some(frame)) => {
+                    return Poll::Ready(Some(decode_event(frame.msg_type, frame.payload)))
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    // framing is lost, nothing after this can be trusted
+                    self.done = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+            let n = match poll_read(&mut self.buf) {
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e))),
+                Poll::Pending => return Poll::Pending,
+            };
+            if n == 0 {
+                self.done = true;
+                return Poll::Ready(if self.decoder.buffered() == 0 {
+                    None
+                } else {
+                    Some(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "stream closed in the middle of a frame",
+                    )))
+                });
+            }
+            self.decoder.feed(&self.buf[..n]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let buf = encode(msg::Msg::RunCommand, Some(b"nop"));
+        assert_eq!(buf.len(), HEADER_LEN + 3);
+        let (frame, len) = decode(&buf).unwrap().unwrap();
+        assert_eq!(len, buf.len());
+        assert_eq!(frame.msg_type, 0);
+        assert_eq!(frame.payload, b"nop");
+        assert!(!frame.is_event());
+    }
+
+    #[test]
+    fn test_empty_payload() {
+        let buf = encode(msg::Msg::Tree, None);
+        let (frame, len) = decode(&buf).unwrap().unwrap();
+        assert_eq!(len, HEADER_LEN);
+        assert_eq!(frame.msg_type, 4);
+        assert!(frame.payload.is_empty());
+    }
+
+    #[test]
+    fn test_bad_magic() {
+        let mut buf = encode(msg::Msg::Tree, None);
+        buf[0] = b'x';
+        let err = decode(&buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_frame_too_large() {
+        let mut header = encode(msg::Msg::Tree, None);
+        header[6..10].copy_from_slice(&u32::MAX.to_ne_bytes());
+        let err = decode(&header).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let buf = encode(msg::Msg::RunCommand, Some(b"0123456789"));
+        assert!(decode_with_limit(&buf, 9).is_err());
+        assert!(decode_with_limit(&buf, 10).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_decode_events_never_panics() {
+        let mut valid = Vec::new();
+        let mut boundaries = vec![0];
+        for (evt, payload) in [
+            (7, &br#"{"first":true,"payload":""}"#[..]),
+            (2, br#"{"change":"default","pango_markup":false}"#),
+            (1, br#"{"change":"unspecified"}"#),
+        ] {
+            valid.extend(MAGIC.as_bytes());
+            valid.extend((payload.len() as u32).to_ne_bytes());
+            valid.extend((evt | 1_u32 << 31).to_ne_bytes());
+            valid.extend(payload);
+            boundaries.push(valid.len());
+        }
+        assert_eq!(decode_events(&valid, 1024).unwrap().len(), 3);
+        // every payload is over 16 bytes
+        let err = decode_events(&valid, 16).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // truncations, single byte mutations and unknown event types
+        let mut seed = 0x2545_f491_u32;
+        for i in 0..valid.len() {
+            match boundaries.iter().position(|b| *b == i) {
+                Some(n) => assert_eq!(decode_events(&valid[..i], 1024).unwrap().len(), n),
+                None => assert!(decode_events(&valid[..i], 1024).is_err()),
+            }
+            let mut mutated = valid.clone();
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            mutated[i] = seed as u8;
+            let _ = decode_events(&mutated, 1024);
+        }
+    }
+
+    #[test]
+    fn test_decoder_partial() {
+        let mut bytes = encode(msg::Msg::RunCommand, Some(b"first"));
+        bytes.extend(encode(msg::Msg::Version, None));
+
+        let mut decoder = Decoder::new();
+        for chunk in bytes.chunks(3) {
+            decoder.feed(chunk);
+        }
+        assert_eq!(decoder.next_frame().unwrap().unwrap().payload, b"first");
+        assert_eq!(decoder.next_frame().unwrap().unwrap().msg_type, 7);
+        assert!(decoder.next_frame().unwrap().is_none());
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn test_decoder_missing() {
+        let bytes = encode(msg::Msg::RunCommand, Some(b"first"));
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.missing().unwrap(), HEADER_LEN);
+        decoder.feed(&bytes[..4]);
+        assert_eq!(decoder.missing().unwrap(), HEADER_LEN - 4);
+        decoder.feed(&bytes[4..HEADER_LEN + 2]);
+        assert_eq!(decoder.missing().unwrap(), 3);
+        decoder.feed(&bytes[HEADER_LEN + 2..]);
+        assert_eq!(decoder.missing().unwrap(), 0);
+        assert_eq!(decoder.next_frame().unwrap().unwrap().payload, b"first");
+    }
+
+    #[test]
+    fn test_event_reader() {
+        let payload = br#"{"change":"unspecified"}"#;
+        let mut bytes = MAGIC.as_bytes().to_vec();
+        bytes.extend((payload.len() as u32).to_ne_bytes());
+        bytes.extend((1 | 1_u32 << 31).to_ne_bytes());
+        bytes.extend(payload);
+        // an event, then half of another
+        let mut input = bytes.clone();
+        input.extend(&bytes[..bytes.len() / 2]);
+
+        let mut reader = EventReader::with_max_frame_len(1024);
+        let mut chunks = input.chunks(5);
+        let mut pending = true;
+        let mut read = |buf: &mut [u8]| {
+            // every other read isn't ready yet
+            pending = !pending;
+            if pending {
+                return Poll::Pending;
+            }
+            let chunk = chunks.next().unwrap_or_default();
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Poll::Ready(Ok(chunk.len()))
+        };
+        let mut next = || loop {
+            if let Poll::Ready(evt) = reader.poll_next(&mut read) {
+                return evt;
+            }
+        };
+        assert!(matches!(next(), Some(Ok(event::Event::Output(_)))));
+        let err = next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(next().is_none());
+    }
+}
@@ -0,0 +1,254 @@
+//! Saving a workspace in the format i3's
+//! [append_layout](https://i3wm.org/docs/layout-saving.html) reads, as
+//! `i3-save-tree` does. Each window becomes a placeholder whose `swallows`
+//! criteria are built from its [WindowProperties](../reply/struct.WindowProperties.html);
+//! once the layout is appended, starting the programs again fills the
+//! placeholders.
+use serde::{Deserialize, Serialize};
+
+use std::io;
+
+use crate::{
+    criteria::escape_regex,
+    reply::{Floating, FullscreenMode, Node, NodeBorder, NodeLayout, NodeType, Rect},
+};
+
+/// Which window properties placeholders match on. Defaults to class and
+/// instance, which is usually enough to tell programs apart without breaking
+/// when a title changes.
+#[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Clone, Copy)]
+#[serde(default)]
+pub struct SwallowProps {
+    pub class: bool,
+    pub instance: bool,
+    pub title: bool,
+    pub window_role: bool,
+    pub machine: bool,
+}
+
+impl Default for SwallowProps {
+    fn default() -> Self {
+        SwallowProps {
+            class: true,
+            instance: true,
+            title: false,
+            window_role: false,
+            machine: false,
+        }
+    }
+}
+
+/// Criteria a placeholder uses to pick up a new window. Values are regular
+/// expressions, anchored and escaped when built from a window.
+#[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Clone, Default)]
+pub struct Swallow {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine: Option<String>,
+}
+
+/// A container in a saved layout, with the keys `append_layout` understands
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct LayoutNode {
+    #[serde(rename = "type")]
+    pub node_type: NodeType,
+    pub border: NodeBorder,
+    pub current_border_width: i32,
+    pub floating: Floating,
+    pub fullscreen_mode: FullscreenMode,
+    pub geometry: Rect,
+    pub layout: NodeLayout,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub marks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percent: Option<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<LayoutNode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub floating_nodes: Vec<LayoutNode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub swallows: Vec<Swallow>,
+}
+
+impl LayoutNode {
+    /// Convert `node` and its children, turning windows into placeholders
+    pub fn from_node(node: &Node, props: &SwallowProps) -> Self {
+        let swallows = node
+            .window_properties
+            .as_ref()
+            .filter(|_| node.window.is_some())
+            .map(|p| {
+                let pick = |want: bool, value: &Option<String>| {
+                    value
+                        .as_ref()
+                        .filter(|_| want)
+                        .map(|v| format!("^{}$", escape_regex(v)))
+                };
+                Swallow {
+                    class: pick(props.class, &p.class),
+                    instance: pick(props.instance, &p.instance),
+                    title: pick(props.title, &p.title),
+                    window_role: pick(props.window_role, &p.window_role),
+                    machine: pick(props.machine, &p.machine),
+                }
+            })
+            .into_iter()
+            .collect();
+        LayoutNode {
+            node_type: node.node_type.clone(),
+            border: node.border.clone(),
+            current_border_width: node.current_border_width,
+            floating: node.floating.clone().unwrap_or(Floating::AutoOff),
+            fullscreen_mode: node.fullscreen_mode,
+            geometry: node.geometry,
+            layout: node.layout.clone(),
+            marks: node.marks.clone().map(|m| m.0).unwrap_or_default(),
+            name: node.name.clone(),
+            percent: node.percent,
+            nodes: node
+                .nodes
+                .iter()
+                .map(|n| LayoutNode::from_node(n, props))
+                .collect(),
+            floating_nodes: node
+                .floating_nodes
+                .iter()
+                .map(|n| LayoutNode::from_node(n, props))
+                .collect(),
+            swallows,
+        }
+    }
+
+    /// Every placeholder in this subtree
+    pub fn placeholders(&self) -> Vec<&LayoutNode> {
+        let mut out = Vec::new();
+        self.collect_placeholders(&mut out);
+        out
+    }
+
+    fn collect_placeholders<'a>(&'a self, out: &mut Vec<&'a LayoutNode>) {
+        if !self.swallows.is_empty() {
+            out.push(self);
+        }
+        for n in self.nodes.iter().chain(&self.floating_nodes) {
+            n.collect_placeholders(out);
+        }
+    }
+}
+
+/// The contents of one workspace, as written by `i3-save-tree --workspace`
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Layout {
+    /// Tiled and floating (`floating_con`) containers, in order
+    pub nodes: Vec<LayoutNode>,
+}
+
+impl Layout {
+    /// Save the children of `workspace`, which should be a workspace node
+    pub fn from_workspace(workspace: &Node, props: &SwallowProps) -> Self {
+        Layout {
+            nodes: workspace
+                .nodes
+                .iter()
+                .chain(&workspace.floating_nodes)
+                .map(|n| LayoutNode::from_node(n, props))
+                .collect(),
+        }
+    }
+
+    /// Serialize as a sequence of JSON objects, the format `append_layout`
+    /// reads
+    pub fn to_json(&self) -> io::Result<String> {
+        let mut out = String::new();
+        for node in &self.nodes {
+            out.push_str(&serde_json::to_string_pretty(node)?);
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// Parse a layout file. Accepts the output of `i3-save-tree`, including
+    /// its `//` comment lines, as well as a single JSON array of containers.
+    pub fn from_json(s: &str) -> io::Result<Self> {
+        let stripped = s
+            .lines()
+            .filter(|l| !l.trim_start().starts_with("//"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if stripped.trim_start().starts_with('[') {
+            return Ok(Layout {
+                nodes: serde_json::from_str(&stripped)?,
+            });
+        }
+        let nodes = serde_json::Deserializer::from_str(&stripped)
+            .into_iter::<LayoutNode>()
+            .collect::<Result<_, _>>()?;
+        Ok(Layout { nodes })
+    }
+
+    /// Every placeholder, i.e. every window that needs starting again
+    pub fn placeholders(&self) -> Vec<&LayoutNode> {
+        self.nodes.iter().flat_map(|n| n.placeholders()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace() -> Node {
+        let tree: Node = serde_json::from_str(include_str!("../test/tree.json")).unwrap();
+        tree.find(|n| n.node_type == NodeType::Workspace && !n.nodes.is_empty())
+            .expect("test tree has a non-empty workspace")
+            .clone()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let ws = workspace();
+        let layout = Layout::from_workspace(&ws, &SwallowProps::default());
+        assert_eq!(layout.nodes.len(), ws.nodes.len() + ws.floating_nodes.len());
+        let json = layout.to_json().unwrap();
+        assert_eq!(Layout::from_json(&json).unwrap(), layout);
+
+        let commented = format!("// vim:ts=4:sw=4:et\n{json}");
+        assert_eq!(Layout::from_json(&commented).unwrap(), layout);
+    }
+
+    #[test]
+    fn test_swallows() {
+        let mut ws = workspace();
+        let win = &mut ws.nodes[0];
+        win.nodes.clear();
+        win.window = Some(1);
+        win.window_properties = serde_json::from_value(serde_json::json!({
+            "class": "Firefox",
+            "instance": "Navigator",
+            "title": "a (b)",
+        }))
+        .unwrap();
+        let props = SwallowProps {
+            instance: false,
+            title: true,
+            ..SwallowProps::default()
+        };
+        let layout = Layout::from_workspace(&ws, &props);
+        assert_eq!(
+            layout.placeholders()[0].swallows,
+            vec![Swallow {
+                class: Some("^Firefox$".into()),
+                title: Some(r"^a \(b\)$".into()),
+                ..Swallow::default()
+            }]
+        );
+    }
+}
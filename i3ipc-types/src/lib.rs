@@ -2,7 +2,7 @@
 //! Also contained is protocol level communication using `io::Read` and `Write`
 use serde::{de::DeserializeOwned, Serialize};
 
-use std::{env, io, os::unix::net::UnixStream as SyncUnixStream, process::Command};
+use std::{io, os::unix::net::UnixStream as SyncUnixStream};
 
 #[cfg(feature = "async-traits")]
 use tokio::net::UnixStream as TokioUnixStream;
@@ -10,9 +10,21 @@ use tokio::net::UnixStream as TokioUnixStream;
 #[cfg(feature = "async-std-traits")]
 use async_std::os::unix::net::UnixStream as AsyncStdUnixStream;
 
+#[macro_use]
+mod macros;
+
+pub mod command;
+pub mod criteria;
 pub mod event;
+pub mod frame;
+pub mod layout;
 pub mod msg;
+pub mod neighbor;
+pub mod output_layout;
 pub mod reply;
+pub mod request;
+pub mod session;
+pub mod socket;
 
 /// Types implementing this are provided a connect function and return a stream
 pub trait Connect {
@@ -29,18 +41,7 @@ pub trait I3Protocol {
     where
         P: AsRef<str>,
     {
-        let mut buf = Vec::with_capacity(14);
-        buf.extend(<Self as I3Protocol>::MAGIC.as_bytes());
-        if let Some(p) = &payload {
-            buf.extend((p.as_ref().len() as u32).to_ne_bytes());
-        } else {
-            buf.extend((0_u32).to_ne_bytes());
-        }
-        buf.extend(<u32 as From<msg::Msg>>::from(msg).to_ne_bytes());
-        if let Some(p) = &payload {
-            buf.extend(p.as_ref().as_bytes());
-        }
-        buf
+        frame::encode(msg, payload.as_ref().map(|p| p.as_ref().as_bytes()))
     }
 
     fn encode_msg(&self, msg: msg::Msg) -> Vec<u8> {
@@ -68,27 +69,18 @@ pub trait I3Protocol {
 
 /// Trait containing methods to encode and decode message from i3
 pub trait I3IPC: io::Read + io::Write + I3Protocol {
+    /// Replies and events with payloads over this many bytes are rejected
+    fn max_frame_len(&self) -> usize {
+        frame::DEFAULT_MAX_FRAME_LEN
+    }
+
     fn decode_msg(&mut self) -> io::Result<(u32, Vec<u8>)> {
-        let mut buf = [0_u8; 6];
+        let mut buf = [0_u8; frame::HEADER_LEN];
         self.read_exact(&mut buf)?;
-        if &buf[..] != <Self as I3Protocol>::MAGIC.as_bytes() {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Expected 'i3-ipc' but received: {buf:?}"),
-            ));
-        }
-        // get payload len
-        let mut intbuf = [0_u8; 4];
-        self.read_exact(&mut intbuf)?;
-        let len = u32::from_ne_bytes(intbuf);
-        // get msg type
-        let mut msgbuf = [0_u8; 4];
-        self.read_exact(&mut msgbuf)?;
-        let msgtype = u32::from_ne_bytes(msgbuf);
-        // get payload
-        let mut payload_buf = vec![0_u8; len as usize];
+        let header = frame::Header::parse_with_limit(&buf, self.max_frame_len())?;
+        let mut payload_buf = vec![0_u8; header.len as usize];
         self.read_exact(&mut payload_buf)?;
-        Ok((msgtype, payload_buf))
+        Ok((header.msg_type, payload_buf))
     }
 }
 
@@ -120,26 +112,16 @@ pub struct MsgResponse<D> {
 impl<D: DeserializeOwned> MsgResponse<D> {
     pub fn new(msg_type: u32, buf: Vec<u8>) -> io::Result<Self> {
         Ok(MsgResponse {
-            msg_type: msg_type.into(),
+            msg_type: msg::Msg::try_from(msg_type)?,
             body: serde_json::from_slice(&buf[..])?,
         })
     }
 }
 
-/// get socket path from i3
+/// get socket path from i3, see [socket](socket/index.html) for the order in
+/// which locations are searched
 pub fn socket_path() -> io::Result<String> {
-    if let Ok(p) = env::var("I3SOCK") {
-        return Ok(p);
-    }
-    let out = Command::new("i3").arg("--get-socketpath").output()?;
-    if out.status.success() {
-        Ok(String::from_utf8_lossy(&out.stdout).trim_end().to_string())
-    } else {
-        Err(io::Error::new(
-            io::ErrorKind::BrokenPipe,
-            "Unable to get i3 socket path",
-        ))
-    }
+    Ok(socket::find()?.path.to_string_lossy().into_owned())
 }
 
 /// Given an event type and payload this function will deserialize the proper
@@ -150,7 +132,7 @@ where
 {
     use event::{Event, Subscribe};
     let evt_type = evt_type & !(1 << 31);
-    let body = match evt_type.into() {
+    let body = match Subscribe::try_from(evt_type)? {
         Subscribe::Workspace => Event::Workspace(Box::new(serde_json::from_slice::<
             event::WorkspaceData,
         >(payload.as_ref())?)),
@@ -163,9 +145,9 @@ where
         Subscribe::Window => Event::Window(Box::new(serde_json::from_slice::<event::WindowData>(
             payload.as_ref(),
         )?)),
-        Subscribe::BarConfigUpdate => Event::BarConfig(serde_json::from_slice::<
+        Subscribe::BarConfigUpdate => Event::BarConfig(Box::new(serde_json::from_slice::<
             event::BarConfigData,
-        >(payload.as_ref())?),
+        >(payload.as_ref())?)),
         Subscribe::Binding => Event::Binding(serde_json::from_slice::<event::BindingData>(
             payload.as_ref(),
         )?),
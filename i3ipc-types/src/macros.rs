@@ -0,0 +1,72 @@
+/// Declares an enum that (de)serializes as a plain string. Strings that don't
+/// match any variant deserialize to `Unknown(String)` instead of failing, so a
+/// value added in a newer i3 or sway doesn't break decoding the whole reply,
+/// and serializing it again round-trips the original string.
+macro_rules! string_enum {
+    (
+        $(#[doc = $doc:literal])*
+        pub enum $name:ident {
+            $(
+                $(#[doc = $vdoc:literal])*
+                $(#[cfg($cfg:meta)])?
+                $variant:ident = $s:literal,
+            )*
+        }
+    ) => {
+        $(#[doc = $doc])*
+        #[derive(Eq, PartialEq, Hash, Debug, Clone)]
+        pub enum $name {
+            $(
+                $(#[doc = $vdoc])*
+                $(#[cfg($cfg)])?
+                $variant,
+            )*
+            /// A value unknown to this version of the crate
+            Unknown(String),
+        }
+
+        impl $name {
+            /// The string i3 uses for this value
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(
+                        $(#[cfg($cfg)])?
+                        $name::$variant => $s,
+                    )*
+                    $name::Unknown(s) => s,
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(s: &str) -> Self {
+                match s {
+                    $(
+                        $(#[cfg($cfg)])?
+                        $s => $name::$variant,
+                    )*
+                    _ => $name::Unknown(s.to_owned()),
+                }
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = String::deserialize(deserializer)?;
+                Ok($name::from(s.as_str()))
+            }
+        }
+    };
+}
@@ -1,4 +1,6 @@
 //! For sending messages to i3
+use std::io;
+
 #[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
 pub enum Msg {
     RunCommand,
@@ -16,9 +18,11 @@ pub enum Msg {
     BindingState,
 }
 
-impl From<u32> for Msg {
-    fn from(num: u32) -> Self {
-        match num {
+impl TryFrom<u32> for Msg {
+    type Error = io::Error;
+
+    fn try_from(num: u32) -> Result<Self, Self::Error> {
+        Ok(match num {
             0 => Msg::RunCommand,
             1 => Msg::Workspaces,
             2 => Msg::Subscribe,
@@ -32,8 +36,13 @@ impl From<u32> for Msg {
             10 => Msg::Tick,
             11 => Msg::Sync,
             12 => Msg::BindingState,
-            _ => panic!("Unknown message type found"),
-        }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown message type found: {num}"),
+                ))
+            }
+        })
     }
 }
 
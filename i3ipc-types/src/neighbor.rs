@@ -0,0 +1,334 @@
+//! Working out where `focus left/right/up/down` would go from a
+//! [Node](../reply/struct.Node.html) tree alone, without asking i3. This
+//! follows i3's own algorithm: walk up from the container to the nearest
+//! split in the right orientation with a sibling in that direction, then
+//! descend into it. Past the edge of a workspace, focus moves to the output
+//! in that direction.
+use serde::{Deserialize, Serialize};
+
+use crate::reply::{Node, NodeLayout, NodeType, Rect};
+
+#[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Direction {
+    pub const fn is_horizontal(&self) -> bool {
+        matches!(self, Direction::Left | Direction::Right)
+    }
+
+    /// True for the directions that go towards the end of a container's
+    /// children
+    const fn is_forward(&self) -> bool {
+        matches!(self, Direction::Right | Direction::Down)
+    }
+
+    pub const fn opposite(&self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+
+    /// Of the rects lying in this direction from `from`, the one you reach
+    /// first. Rects overlapping `from` across the direction of travel win
+    /// over ones that don't, then the smallest gap wins, then the one most
+    /// nearly in line.
+    pub fn nearest<T, I>(&self, from: &Rect, candidates: I) -> Option<T>
+    where
+        I: IntoIterator<Item = (Rect, T)>,
+    {
+        candidates
+            .into_iter()
+            .filter_map(|(rect, item)| self.score(from, &rect).map(|s| (s, item)))
+            .min_by_key(|(score, _)| *score)
+            .map(|(_, item)| item)
+    }
+
+    /// `None` if `to` isn't in this direction, otherwise a sort key
+    fn score(&self, from: &Rect, to: &Rect) -> Option<(bool, isize, isize)> {
+        let (fc, tc) = (from.center(), to.center());
+        let (ahead, gap, off_axis) = match self {
+            Direction::Left => (tc.0 < fc.0, from.x - to.right(), tc.1 - fc.1),
+            Direction::Right => (tc.0 > fc.0, to.x - from.right(), tc.1 - fc.1),
+            Direction::Up => (tc.1 < fc.1, from.y - to.bottom(), tc.0 - fc.0),
+            Direction::Down => (tc.1 > fc.1, to.y - from.bottom(), tc.0 - fc.0),
+        };
+        let overlap = self.overlaps_across(from, to);
+        ahead.then_some((!overlap, gap.max(0), off_axis.abs()))
+    }
+
+    /// True if `from` and `to` overlap across the direction of travel
+    pub(crate) fn overlaps_across(&self, from: &Rect, to: &Rect) -> bool {
+        if self.is_horizontal() {
+            overlaps(from.y, from.bottom(), to.y, to.bottom())
+        } else {
+            overlaps(from.x, from.right(), to.x, to.right())
+        }
+    }
+}
+
+fn overlaps(a0: isize, a1: isize, b0: isize, b1: isize) -> bool {
+    a0 < b1 && b0 < a1
+}
+
+/// Where focus would land
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum Neighbor<'a> {
+    Window(&'a Node),
+    /// An output whose visible workspace is empty
+    Output(&'a Node),
+}
+
+impl<'a> Neighbor<'a> {
+    pub fn node(&self) -> &'a Node {
+        match self {
+            Neighbor::Window(n) | Neighbor::Output(n) => n,
+        }
+    }
+}
+
+/// Find where focus would move from container `id` in direction `dir`.
+/// With `floating`, floating windows on visible workspaces are candidates
+/// too, picked by position when nearer than the tiling neighbor. `None` if
+/// `id` isn't in `tree` or nothing lies in that direction.
+pub fn neighbor(tree: &Node, id: usize, dir: Direction, floating: bool) -> Option<Neighbor<'_>> {
+    let path = path_to(tree, id)?;
+    let from = *path.last()?;
+    let is_floating = path.iter().any(|n| n.node_type == NodeType::FloatingCon);
+
+    let tiled = if is_floating {
+        None
+    } else {
+        tiled_neighbor(&path, dir).or_else(|| output_neighbor(tree, &path, dir))
+    };
+    if !floating && !is_floating {
+        return tiled;
+    }
+
+    let floats = visible_workspaces(tree)
+        .flat_map(|(_, ws)| ws.floating_nodes.iter())
+        .flat_map(leaves)
+        .filter(|n| n.id != from.id)
+        .map(|n| (n.rect, Neighbor::Window(n)));
+    let tiled_rect = tiled.map(|t| (t.node().rect, t));
+    match (is_floating, tiled) {
+        // nothing to walk up from, go by position alone
+        (true, _) => {
+            let all_tiled = visible_workspaces(tree)
+                .flat_map(|(_, ws)| ws.nodes.iter())
+                .flat_map(leaves)
+                .map(|n| (n.rect, Neighbor::Window(n)));
+            dir.nearest(&from.rect, floats.chain(all_tiled))
+        }
+        _ => dir.nearest(&from.rect, floats.chain(tiled_rect)).or(tiled),
+    }
+}
+
+fn tiled_neighbor<'a>(path: &[&'a Node], dir: Direction) -> Option<Neighbor<'a>> {
+    // path is root -> .. -> workspace -> .. -> container
+    for i in (1..path.len()).rev() {
+        let (parent, child) = (path[i - 1], path[i]);
+        if child.node_type == NodeType::Workspace {
+            break;
+        }
+        if is_horizontal(&parent.layout) != Some(dir.is_horizontal()) {
+            continue;
+        }
+        let idx = parent.nodes.iter().position(|n| n.id == child.id)?;
+        let sibling = if dir.is_forward() {
+            parent.nodes.get(idx + 1)
+        } else {
+            idx.checked_sub(1).and_then(|i| parent.nodes.get(i))
+        };
+        if let Some(sibling) = sibling {
+            return Some(Neighbor::Window(descend(sibling, dir)));
+        }
+    }
+    None
+}
+
+fn output_neighbor<'a>(tree: &'a Node, path: &[&'a Node], dir: Direction) -> Option<Neighbor<'a>> {
+    let current = path.iter().find(|n| n.node_type == NodeType::Output)?;
+    // like i3, only outputs overlapping the current one across the
+    // direction of travel count
+    let (output, ws) = dir.nearest(
+        &current.rect,
+        visible_workspaces(tree)
+            .filter(|(o, _)| o.id != current.id && dir.overlaps_across(&current.rect, &o.rect))
+            .map(|(o, ws)| (o.rect, (o, ws))),
+    )?;
+    if ws.nodes.is_empty() {
+        return Some(Neighbor::Output(output));
+    }
+    Some(Neighbor::Window(descend(ws, dir)))
+}
+
+/// Pick the child i3 would focus on entering `node` moving in `dir`: the
+/// nearest edge of a split in the same orientation, otherwise the focused
+/// child
+fn descend(node: &Node, dir: Direction) -> &Node {
+    let mut node = node;
+    while !node.nodes.is_empty() {
+        node = if is_horizontal(&node.layout) == Some(dir.is_horizontal()) {
+            if dir.is_forward() {
+                &node.nodes[0]
+            } else {
+                &node.nodes[node.nodes.len() - 1]
+            }
+        } else {
+            focused_child(node)
+        };
+    }
+    node
+}
+
+/// `node` must have children
+fn focused_child(node: &Node) -> &Node {
+    node.focus
+        .iter()
+        .find_map(|id| node.nodes.iter().find(|n| n.id == *id))
+        .unwrap_or(&node.nodes[0])
+}
+
+/// Orientation children are laid out in, tabs count as horizontal and stacks
+/// as vertical like in i3
+fn is_horizontal(layout: &NodeLayout) -> Option<bool> {
+    match layout {
+        NodeLayout::SplitH | NodeLayout::Tabbed => Some(true),
+        NodeLayout::SplitV | NodeLayout::Stacked => Some(false),
+        _ => None,
+    }
+}
+
+/// Each real output paired with the workspace visible on it
+fn visible_workspaces(tree: &Node) -> impl Iterator<Item = (&Node, &Node)> {
+    tree.nodes
+        .iter()
+        .filter(|o| o.node_type == NodeType::Output && o.name.as_deref() != Some("__i3"))
+        .filter_map(|o| {
+            let content = o
+                .nodes
+                .iter()
+                .find(|n| n.node_type == NodeType::Con && n.name.as_deref() == Some("content"))?;
+            (!content.nodes.is_empty()).then(|| (o, focused_child(content)))
+        })
+}
+
+/// Windows under `node`, hidden tabs and stacked windows excluded
+fn leaves(node: &Node) -> Vec<&Node> {
+    if node.nodes.is_empty() {
+        return if node.window.is_some() {
+            vec![node]
+        } else {
+            Vec::new()
+        };
+    }
+    match node.layout {
+        NodeLayout::Tabbed | NodeLayout::Stacked => leaves(focused_child(node)),
+        _ => node.nodes.iter().flat_map(leaves).collect(),
+    }
+}
+
+/// Containers from `node` down to `id`, both included
+pub fn path_to(node: &Node, id: usize) -> Option<Vec<&Node>> {
+    if node.id == id {
+        return Some(vec![node]);
+    }
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .find_map(|n| path_to(n, id))
+        .map(|mut path| {
+            path.insert(0, node);
+            path
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IPC_DOCS: usize = 106996225532800;
+    const GHCI: usize = 106996225511040;
+    const SHELL: usize = 106996225510400;
+    const VSCODE: usize = 106996225509760;
+    const FRANCIS: usize = 106996225526400;
+
+    fn tree() -> Node {
+        serde_json::from_str(include_str!("../test/tree.json")).unwrap()
+    }
+
+    fn id(n: Option<Neighbor<'_>>) -> Option<usize> {
+        n.map(|n| n.node().id)
+    }
+
+    #[test]
+    fn test_within_workspace() {
+        let tree = tree();
+        assert_eq!(
+            id(neighbor(&tree, GHCI, Direction::Down, false)),
+            Some(SHELL)
+        );
+        assert_eq!(id(neighbor(&tree, SHELL, Direction::Up, false)), Some(GHCI));
+        assert_eq!(
+            id(neighbor(&tree, GHCI, Direction::Left, false)),
+            Some(VSCODE)
+        );
+        // moving left into a vertical split lands on its focused child
+        assert_eq!(
+            id(neighbor(&tree, IPC_DOCS, Direction::Left, false)),
+            Some(FRANCIS)
+        );
+        assert_eq!(id(neighbor(&tree, GHCI, Direction::Up, false)), None);
+    }
+
+    #[test]
+    fn test_across_outputs() {
+        let tree = tree();
+        // DVI-I-2 is left of DVI-I-3, entering from the left lands on the
+        // leftmost window
+        assert_eq!(
+            id(neighbor(&tree, IPC_DOCS, Direction::Right, false)),
+            Some(VSCODE)
+        );
+        assert_eq!(
+            id(neighbor(&tree, VSCODE, Direction::Left, false)),
+            Some(IPC_DOCS)
+        );
+        assert_eq!(id(neighbor(&tree, GHCI, Direction::Right, false)), None);
+    }
+
+    #[test]
+    fn test_outputs_must_overlap() {
+        let mut tree = tree();
+        // move DVI-I-2 down and to the left, out of line with DVI-I-3
+        let left = tree
+            .nodes
+            .iter_mut()
+            .find(|n| n.name.as_deref() == Some("DVI-I-2"))
+            .unwrap();
+        left.rect.y += 10_000;
+        assert_eq!(id(neighbor(&tree, VSCODE, Direction::Left, false)), None);
+    }
+
+    #[test]
+    fn test_nearest() {
+        let from = Rect::new(100, 100, 100, 100);
+        let rects = [
+            (Rect::new(0, 100, 50, 100), "left"),
+            (Rect::new(60, 400, 30, 30), "left, not in line"),
+            (Rect::new(300, 150, 100, 100), "right"),
+        ];
+        assert_eq!(Direction::Left.nearest(&from, rects), Some("left"));
+        assert_eq!(Direction::Right.nearest(&from, rects), Some("right"));
+        assert_eq!(Direction::Up.nearest(&from, rects), None);
+    }
+}
@@ -0,0 +1,201 @@
+//! How outputs are arranged and which workspaces they hold, built from the
+//! replies to `get_outputs` and `get_workspaces`
+use crate::{
+    neighbor::Direction,
+    reply::{Output, Rect, Workspace},
+};
+
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct OutputLayout {
+    outputs: Vec<Output>,
+    workspaces: Vec<Workspace>,
+}
+
+impl OutputLayout {
+    /// Inactive outputs are dropped, they have no place in the layout
+    pub fn new(outputs: Vec<Output>, workspaces: Vec<Workspace>) -> Self {
+        OutputLayout {
+            outputs: outputs.into_iter().filter(|o| o.active).collect(),
+            workspaces,
+        }
+    }
+
+    /// Active outputs, in the order i3 listed them
+    pub fn outputs(&self) -> &[Output] {
+        &self.outputs
+    }
+
+    pub fn workspaces(&self) -> &[Workspace] {
+        &self.workspaces
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Output> {
+        self.outputs.iter().find(|o| o.name == name)
+    }
+
+    /// The output marked primary, or the first one if none is
+    pub fn primary(&self) -> Option<&Output> {
+        self.outputs
+            .iter()
+            .find(|o| o.primary)
+            .or_else(|| self.outputs.first())
+    }
+
+    pub fn at_point(&self, x: isize, y: isize) -> Option<&Output> {
+        self.outputs.iter().find(|o| o.rect.contains_point(x, y))
+    }
+
+    /// The output `rect` overlaps the most, as i3 decides where a floating
+    /// window belongs
+    pub fn containing(&self, rect: &Rect) -> Option<&Output> {
+        self.outputs
+            .iter()
+            .filter_map(|o| o.rect.intersection(rect).map(|i| (i.area(), o)))
+            .max_by_key(|(area, _)| *area)
+            .map(|(_, o)| o)
+    }
+
+    /// The output next to `name` in direction `dir`. Like i3, only outputs
+    /// overlapping `name` across that direction count.
+    pub fn neighbor(&self, name: &str, dir: Direction) -> Option<&Output> {
+        let from = self.get(name)?;
+        dir.nearest(
+            &from.rect,
+            self.outputs
+                .iter()
+                .filter(|o| o.name != name && dir.overlaps_across(&from.rect, &o.rect))
+                .map(|o| (o.rect, o)),
+        )
+    }
+
+    /// Every output touching `name`, with the direction it lies in. Outputs
+    /// that only meet at a corner don't count.
+    pub fn adjacent(&self, name: &str) -> Vec<(Direction, &Output)> {
+        let Some(from) = self.get(name) else {
+            return Vec::new();
+        };
+        let r = &from.rect;
+        self.outputs
+            .iter()
+            .filter(|o| o.name != name)
+            .filter_map(|o| {
+                let t = &o.rect;
+                let across_y = r.y < t.bottom() && t.y < r.bottom();
+                let across_x = r.x < t.right() && t.x < r.right();
+                let dir = if across_y && t.right() == r.x {
+                    Direction::Left
+                } else if across_y && t.x == r.right() {
+                    Direction::Right
+                } else if across_x && t.bottom() == r.y {
+                    Direction::Up
+                } else if across_x && t.y == r.bottom() {
+                    Direction::Down
+                } else {
+                    return None;
+                };
+                Some((dir, o))
+            })
+            .collect()
+    }
+
+    /// Workspaces on output `name`
+    pub fn workspaces_on<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Workspace> {
+        self.workspaces.iter().filter(move |w| w.output == name)
+    }
+
+    /// The workspace currently shown on output `name`
+    pub fn visible_workspace(&self, name: &str) -> Option<&Workspace> {
+        self.workspaces
+            .iter()
+            .find(|w| w.output == name && w.visible)
+    }
+
+    /// The output holding workspace `name`
+    pub fn output_of(&self, workspace: &str) -> Option<&Output> {
+        let ws = self.workspaces.iter().find(|w| w.name == workspace)?;
+        self.get(&ws.output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(name: &str, rect: Rect, primary: bool) -> Output {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "active": true,
+            "primary": primary,
+            "rect": rect,
+            "current_workspace": null,
+        }))
+        .unwrap()
+    }
+
+    fn workspace(name: &str, output: &str, visible: bool) -> Workspace {
+        serde_json::from_value(serde_json::json!({
+            "num": -1,
+            "name": name,
+            "visible": visible,
+            "focused": false,
+            "urgent": false,
+            "rect": Rect::default(),
+            "output": output,
+            "focus": [],
+        }))
+        .unwrap()
+    }
+
+    fn layout() -> OutputLayout {
+        // [ left ][ main ]
+        //         [ below ]
+        OutputLayout::new(
+            vec![
+                output("left", Rect::new(0, 0, 1920, 1080), false),
+                output("main", Rect::new(1920, 0, 2560, 1440), true),
+                output("below", Rect::new(1920, 1440, 1920, 1080), false),
+            ],
+            vec![
+                workspace("1", "main", true),
+                workspace("2", "main", false),
+                workspace("3", "left", true),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_lookup() {
+        let l = layout();
+        assert_eq!(l.primary().unwrap().name, "main");
+        assert_eq!(l.at_point(100, 100).unwrap().name, "left");
+        assert_eq!(l.at_point(1920, 1500).unwrap().name, "below");
+        assert!(l.at_point(100, 1500).is_none());
+        let straddling = Rect::new(1800, 100, 400, 300);
+        assert_eq!(l.containing(&straddling).unwrap().name, "main");
+        assert_eq!(l.output_of("3").unwrap().name, "left");
+        assert_eq!(l.visible_workspace("main").unwrap().name, "1");
+        assert_eq!(l.workspaces_on("main").count(), 2);
+    }
+
+    #[test]
+    fn test_adjacency() {
+        let l = layout();
+        let names = |v: Vec<(Direction, &Output)>| {
+            v.into_iter()
+                .map(|(d, o)| (d, o.name.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(l.adjacent("main")),
+            vec![
+                (Direction::Left, "left".to_owned()),
+                (Direction::Down, "below".to_owned())
+            ]
+        );
+        assert_eq!(l.neighbor("below", Direction::Up).unwrap().name, "main");
+        assert!(l.neighbor("main", Direction::Right).is_none());
+        // "main" reaches further down but lies beside "left", and "below"
+        // is off to the side
+        assert!(l.neighbor("left", Direction::Down).is_none());
+    }
+}
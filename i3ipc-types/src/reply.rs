@@ -1,15 +1,20 @@
 //! Contains structs for deserializing messages from i3
-use serde::{Deserialize, Serialize};
-use serde_repr::{Deserialize_repr, Serialize_repr};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::{fmt, io, str::FromStr};
+
+pub use crate::event::Modifiers;
 
 /// Generic success reply
 #[derive(Deserialize, Serialize, Eq, PartialEq, Clone, Hash, Debug)]
 pub struct Success {
     pub success: bool,
     pub error: Option<String>,
+    /// Set when i3 couldn't parse the command at all, rather than it failing
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub parse_error: bool,
 }
 
 /// Workspaces reply
@@ -29,6 +34,10 @@ pub struct Workspace {
     pub output: String,
     #[cfg(feature = "sway")]
     pub focus: Vec<usize>,
+    /// Fields unknown to this version of the crate, kept so they survive a
+    /// round trip
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Outputs reply
@@ -41,6 +50,10 @@ pub struct Output {
     pub primary: bool,
     pub current_workspace: Option<String>,
     pub rect: Rect,
+    /// Fields unknown to this version of the crate, kept so they survive a
+    /// round trip
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Tree/Node reply
@@ -77,6 +90,45 @@ pub struct Node {
     pub nodes: Vec<Node>,
     #[cfg(feature = "sway")]
     pub app_id: Option<String>,
+    /// Fields unknown to this version of the crate, kept so they survive a
+    /// round trip
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Node {
+    /// Depth-first search of this node and everything below it, floating
+    /// nodes included
+    pub fn find<F: Fn(&Node) -> bool>(&self, pred: F) -> Option<&Node> {
+        self.find_dyn(&pred)
+    }
+
+    fn find_dyn(&self, pred: &dyn Fn(&Node) -> bool) -> Option<&Node> {
+        if pred(self) {
+            return Some(self);
+        }
+        self.nodes
+            .iter()
+            .chain(&self.floating_nodes)
+            .find_map(|n| n.find_dyn(pred))
+    }
+
+    /// The client window's rect in absolute coordinates. `window_rect` is
+    /// relative to the container's `rect`.
+    pub fn absolute_window_rect(&self) -> Rect {
+        self.window_rect.to_absolute(&self.rect)
+    }
+
+    /// The title bar's rect in absolute coordinates. `deco_rect` is relative
+    /// to the parent container, which must be passed in.
+    pub fn absolute_deco_rect(&self, parent: &Node) -> Rect {
+        self.deco_rect.to_absolute(&parent.rect)
+    }
+
+    /// The workspace named `name`
+    pub fn find_workspace(&self, name: &str) -> Option<&Node> {
+        self.find(|n| n.node_type == NodeType::Workspace && n.name.as_deref() == Some(name))
+    }
 }
 
 impl PartialEq for Node {
@@ -104,60 +156,44 @@ impl<'de> serde::Deserialize<'de> for WindowProperties {
     where
         D: serde::Deserializer<'de>,
     {
-        #[derive(Deserialize, Serialize, Eq, PartialEq, Clone, Debug)]
-        struct Intermediate(HashMap<WindowProperty, Option<WindowData>>);
-
-        #[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
-        #[serde(untagged)]
-        enum WindowData {
-            Str(String),
-            Num(u64),
-        }
-        impl WindowData {
-            fn unwrap_str(self) -> String {
-                match self {
-                    WindowData::Str(s) => s,
-                    _ => unreachable!("cant have non-string value"),
-                }
+        #[derive(Deserialize)]
+        struct Intermediate(HashMap<WindowProperty, serde_json::Value>);
+
+        fn into_str<E: serde::de::Error>(value: serde_json::Value) -> Result<Option<String>, E> {
+            match value {
+                serde_json::Value::String(s) => Ok(Some(s)),
+                serde_json::Value::Null => Ok(None),
+                other => Err(E::custom(format!(
+                    "invalid type: {other}, expected a string"
+                ))),
             }
+        }
 
-            fn unwrap_num(self) -> u64 {
-                match self {
-                    WindowData::Num(n) => n,
-                    _ => unreachable!("cant have non-num value"),
-                }
+        fn into_num<E: serde::de::Error>(value: serde_json::Value) -> Result<Option<u64>, E> {
+            match value {
+                serde_json::Value::Number(n) if n.is_u64() => Ok(n.as_u64()),
+                serde_json::Value::Null => Ok(None),
+                other => Err(E::custom(format!(
+                    "invalid type: {other}, expected an unsigned integer"
+                ))),
             }
         }
-        let mut input = Intermediate::deserialize(deserializer)?;
-        let title = input
-            .0
-            .get_mut(&WindowProperty::Title)
-            .and_then(|x| x.take().map(|x| x.unwrap_str()));
-        let instance = input
-            .0
-            .get_mut(&WindowProperty::Instance)
-            .and_then(|x| x.take().map(|x| x.unwrap_str()));
-        let class = input
-            .0
-            .get_mut(&WindowProperty::Class)
-            .and_then(|x| x.take().map(|x| x.unwrap_str()));
-        let window_role = input
-            .0
-            .get_mut(&WindowProperty::WindowRole)
-            .and_then(|x| x.take().map(|x| x.unwrap_str()));
+
+        // unknown properties are ignored
+        let mut input = Intermediate::deserialize(deserializer)?.0;
         let transient_for = input
-            .0
-            .get_mut(&WindowProperty::TransientFor)
-            .and_then(|x| x.take().map(|x| x.unwrap_num()));
-        let machine = input
-            .0
-            .get_mut(&WindowProperty::Machine)
-            .and_then(|x| x.take().map(|x| x.unwrap_str()));
+            .remove(&WindowProperty::TransientFor)
+            .map_or(Ok(None), into_num)?;
+        let mut string = |prop: WindowProperty| -> Result<Option<String>, D::Error> {
+            input.remove(&prop).map_or(Ok(None), into_str)
+        };
+        let title = string(WindowProperty::Title)?;
+        let instance = string(WindowProperty::Instance)?;
+        let class = string(WindowProperty::Class)?;
+        let window_role = string(WindowProperty::WindowRole)?;
+        let machine = string(WindowProperty::Machine)?;
         #[cfg(feature = "sway")]
-        let window_type = input
-            .0
-            .get_mut(&WindowProperty::WindowType)
-            .and_then(|x| x.take().map(|x| x.unwrap_str()));
+        let window_type = string(WindowProperty::WindowType)?;
 
         Ok(WindowProperties {
             title,
@@ -172,64 +208,89 @@ impl<'de> serde::Deserialize<'de> for WindowProperties {
     }
 }
 
-#[derive(Deserialize, Serialize, Eq, PartialEq, Copy, Clone, Hash, Debug)]
-#[serde(rename_all = "snake_case")]
-pub enum Floating {
-    AutoOff,
-    AutoOn,
-    UserOff,
-    UserOn,
+string_enum! {
+    pub enum Floating {
+        AutoOff = "auto_off",
+        AutoOn = "auto_on",
+        UserOff = "user_off",
+        UserOn = "user_on",
+    }
 }
 
-#[derive(Deserialize_repr, Serialize_repr, Eq, PartialEq, Copy, Clone, Hash, Debug)]
-#[repr(u8)]
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
 pub enum FullscreenMode {
-    None = 0,
-    Output = 1,
-    Global = 2,
-}
-
-#[derive(Deserialize, Serialize, Eq, PartialEq, Clone, Copy, Hash, Debug)]
-#[serde(rename_all = "snake_case")]
-pub enum WindowProperty {
-    Title,
-    Instance,
-    Class,
-    WindowRole,
-    TransientFor,
-    Machine,
-    #[cfg(feature = "sway")]
-    WindowType,
-}
-
-#[derive(Deserialize, Serialize, Eq, PartialEq, Clone, Copy, Hash, Debug)]
-#[serde(rename_all = "snake_case")]
-pub enum WindowType {
-    Normal,
-    Dock,
-    Dialog,
-    Utility,
-    Toolbar,
-    Splash,
-    Menu,
-    DropdownMenu,
-    PopupMenu,
-    Tooltip,
-    Notification,
-    Unknown,
-}
-
-#[cfg(feature = "sway")]
-#[derive(Deserialize, Serialize, Eq, PartialEq, Clone, Hash, Debug)]
-pub struct Rect {
-    pub x: isize,
-    pub y: isize,
-    pub width: isize,
-    pub height: isize,
+    None,
+    Output,
+    Global,
+    /// A mode unknown to this version of the crate
+    Unknown(u8),
 }
 
-#[cfg(not(feature = "sway"))]
-#[derive(Deserialize, Serialize, Eq, PartialEq, Clone, Hash, Debug)]
+impl From<u8> for FullscreenMode {
+    fn from(mode: u8) -> Self {
+        match mode {
+            0 => FullscreenMode::None,
+            1 => FullscreenMode::Output,
+            2 => FullscreenMode::Global,
+            n => FullscreenMode::Unknown(n),
+        }
+    }
+}
+
+impl From<FullscreenMode> for u8 {
+    fn from(mode: FullscreenMode) -> Self {
+        match mode {
+            FullscreenMode::None => 0,
+            FullscreenMode::Output => 1,
+            FullscreenMode::Global => 2,
+            FullscreenMode::Unknown(n) => n,
+        }
+    }
+}
+
+impl Serialize for FullscreenMode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8((*self).into())
+    }
+}
+
+impl<'de> Deserialize<'de> for FullscreenMode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(u8::deserialize(deserializer)?.into())
+    }
+}
+
+string_enum! {
+    pub enum WindowProperty {
+        Title = "title",
+        Instance = "instance",
+        Class = "class",
+        WindowRole = "window_role",
+        TransientFor = "transient_for",
+        Machine = "machine",
+        #[cfg(feature = "sway")]
+        WindowType = "window_type",
+    }
+}
+
+string_enum! {
+    /// `Unknown("unknown")` is used for windows i3 couldn't determine a type for
+    pub enum WindowType {
+        Normal = "normal",
+        Dock = "dock",
+        Dialog = "dialog",
+        Utility = "utility",
+        Toolbar = "toolbar",
+        Splash = "splash",
+        Menu = "menu",
+        DropdownMenu = "dropdown_menu",
+        PopupMenu = "popup_menu",
+        Tooltip = "tooltip",
+        Notification = "notification",
+    }
+}
+
+#[derive(Deserialize, Serialize, Eq, PartialEq, Clone, Copy, Hash, Debug, Default)]
 pub struct Rect {
     pub x: isize,
     pub y: isize,
@@ -237,54 +298,179 @@ pub struct Rect {
     pub height: isize,
 }
 
-#[derive(Deserialize, Serialize, Eq, PartialEq, Clone, Hash, Debug, Copy)]
-#[serde(rename_all = "snake_case")]
-pub enum NodeType {
-    Root,
-    Output,
-    Con,
-    FloatingCon,
-    Workspace,
-    Dockarea,
+impl Rect {
+    pub const fn new(x: isize, y: isize, width: isize, height: isize) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// One past the rightmost column
+    pub const fn right(&self) -> isize {
+        self.x + self.width
+    }
+
+    /// One past the bottom row
+    pub const fn bottom(&self) -> isize {
+        self.y + self.height
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+
+    pub const fn area(&self) -> isize {
+        if self.is_empty() {
+            0
+        } else {
+            self.width * self.height
+        }
+    }
+
+    pub const fn center(&self) -> (isize, isize) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+
+    pub const fn contains_point(&self, x: isize, y: isize) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+
+    /// True if `other` lies entirely within `self`
+    pub const fn contains(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.right() <= self.right()
+            && other.bottom() <= self.bottom()
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// The overlapping area, `None` if the rects don't overlap
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let r = Rect::new(
+            x,
+            y,
+            self.right().min(other.right()) - x,
+            self.bottom().min(other.bottom()) - y,
+        );
+        (!r.is_empty()).then_some(r)
+    }
+
+    /// The smallest rect containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(
+            x,
+            y,
+            self.right().max(other.right()) - x,
+            self.bottom().max(other.bottom()) - y,
+        )
+    }
+
+    pub const fn translate(&self, dx: isize, dy: isize) -> Rect {
+        Rect::new(self.x + dx, self.y + dy, self.width, self.height)
+    }
+
+    /// Move `self` inside `bounds`, shrinking it if it is too big to fit
+    pub fn clamp_to(&self, bounds: &Rect) -> Rect {
+        let width = self.width.min(bounds.width);
+        let height = self.height.min(bounds.height);
+        Rect::new(
+            self.x.clamp(bounds.x, bounds.right() - width),
+            self.y.clamp(bounds.y, bounds.bottom() - height),
+            width,
+            height,
+        )
+    }
+
+    /// Distance from each edge of `self` to the same edge of `outer`, positive
+    /// when `self` is inside. Useful for snapping to screen edges.
+    pub const fn edge_distances(&self, outer: &Rect) -> Edges {
+        Edges {
+            top: self.y - outer.y,
+            right: outer.right() - self.right(),
+            bottom: outer.bottom() - self.bottom(),
+            left: self.x - outer.x,
+        }
+    }
+
+    /// Convert a rect relative to `container`, like a node's `window_rect`
+    /// or `deco_rect`, into absolute coordinates
+    pub const fn to_absolute(&self, container: &Rect) -> Rect {
+        self.translate(container.x, container.y)
+    }
+
+    /// Convert an absolute rect into one relative to `container`
+    pub const fn to_relative(&self, container: &Rect) -> Rect {
+        self.translate(-container.x, -container.y)
+    }
 }
-#[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Clone)]
-#[serde(rename_all = "lowercase")]
-pub enum NodeBorder {
-    Normal,
-    None,
-    Pixel,
-    #[cfg(feature = "sway")]
-    CSD,
+
+/// Per-edge distances, see [Rect::edge_distances](struct.Rect.html#method.edge_distances)
+#[derive(Eq, PartialEq, Clone, Copy, Hash, Debug, Default)]
+pub struct Edges {
+    pub top: isize,
+    pub right: isize,
+    pub bottom: isize,
+    pub left: isize,
 }
 
-#[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Clone, Copy)]
-#[serde(rename_all = "lowercase")]
-pub enum NodeLayout {
-    SplitH,
-    SplitV,
-    Stacked,
-    Tabbed,
-    Dockarea,
-    Output,
-    #[cfg(feature = "sway")]
-    None,
+string_enum! {
+    pub enum NodeType {
+        Root = "root",
+        Output = "output",
+        Con = "con",
+        FloatingCon = "floating_con",
+        Workspace = "workspace",
+        Dockarea = "dockarea",
+    }
+}
+string_enum! {
+    pub enum NodeBorder {
+        Normal = "normal",
+        None = "none",
+        Pixel = "pixel",
+        #[cfg(feature = "sway")]
+        CSD = "csd",
+    }
 }
 
-#[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Clone, Copy)]
-#[serde(rename_all = "lowercase")]
-pub enum NodeOrientation {
-    Horizontal,
-    Vertical,
-    None,
+string_enum! {
+    pub enum NodeLayout {
+        SplitH = "splith",
+        SplitV = "splitv",
+        Stacked = "stacked",
+        Tabbed = "tabbed",
+        Dockarea = "dockarea",
+        Output = "output",
+        #[cfg(feature = "sway")]
+        None = "none",
+    }
+}
+
+string_enum! {
+    pub enum NodeOrientation {
+        Horizontal = "horizontal",
+        Vertical = "vertical",
+        None = "none",
+    }
 }
 
 #[cfg(not(feature = "sway"))]
-#[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Clone, Copy)]
-#[serde(rename_all = "lowercase")]
-pub enum ScratchpadState {
-    None,
-    Fresh,
-    Changed,
+string_enum! {
+    pub enum ScratchpadState {
+        None = "none",
+        Fresh = "fresh",
+        Changed = "changed",
+    }
 }
 
 /// Marks Reply
@@ -299,40 +485,187 @@ pub struct BarIds(pub Vec<String>);
 #[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone)]
 pub struct BarConfig {
     pub id: String,
-    pub mode: String,
-    pub position: String,
-    pub status_command: String,
-    pub font: String,
+    /// Outputs the bar is shown on, empty means all of them
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    /// Outputs the tray is shown on, from `tray_output`
+    #[serde(default, rename = "tray_outputs", alias = "tray_output")]
+    pub tray_output: Vec<String>,
+    #[serde(default)]
+    pub tray_padding: u32,
+    pub mode: BarMode,
+    pub hidden_state: Option<HiddenState>,
+    /// Modifier used to toggle `hide` mode
+    #[serde(default, with = "modifier_mask")]
+    pub modifier: Modifiers,
+    #[serde(default)]
+    pub bindings: Vec<BarBinding>,
+    pub position: BarPosition,
+    pub status_command: Option<String>,
+    pub font: Option<String>,
+    pub separator_symbol: Option<String>,
     pub workspace_buttons: bool,
+    #[serde(default)]
+    pub workspace_min_width: u32,
+    #[serde(default)]
+    pub strip_workspace_numbers: bool,
+    #[serde(default)]
+    pub strip_workspace_name: bool,
     pub binding_mode_indicator: bool,
     pub verbose: bool,
-    pub colors: HashMap<BarPart, String>,
+    /// Padding around the status line, sway only
+    pub status_padding: Option<u32>,
+    pub colors: HashMap<BarPart, Rgba>,
+    /// Fields unknown to this version of the crate, kept so they survive a
+    /// round trip
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
+string_enum! {
+    pub enum BarMode {
+        Dock = "dock",
+        Hide = "hide",
+        Invisible = "invisible",
+        /// sway only
+        Overlay = "overlay",
+    }
+}
+
+string_enum! {
+    pub enum BarPosition {
+        Top = "top",
+        Bottom = "bottom",
+    }
+}
+
+string_enum! {
+    pub enum HiddenState {
+        Hide = "hide",
+        Show = "show",
+    }
+}
+
+/// A mouse binding from the bar's `bindsym buttonN` lines
 #[derive(Deserialize, Serialize, Eq, PartialEq, Hash, Debug, Clone)]
-#[serde(rename_all = "snake_case")]
-pub enum BarPart {
-    Background,
-    Statusline,
-    Separator,
-    FocusedBackground,
-    FocusedStatusline,
-    FocusedSeparator,
-    FocusedWorkspaceText,
-    FocusedWorkspaceBg,
-    FocusedWorkspaceBorder,
-    ActiveWorkspaceText,
-    ActiveWorkspaceBg,
-    ActiveWorkspaceBorder,
-    InactiveWorkspaceText,
-    InactiveWorkspaceBg,
-    InactiveWorkspaceBorder,
-    UrgentWorkspaceText,
-    UrgentWorkspaceBg,
-    UrgentWorkspaceBorder,
-    BindingModeText,
-    BindingModeBg,
-    BindingModeBorder,
+pub struct BarBinding {
+    /// Mouse button number
+    #[serde(alias = "event_code")]
+    pub input_code: u32,
+    pub command: String,
+    #[serde(default)]
+    pub release: bool,
+}
+
+/// i3 sends the bar modifier as an X modifier mask, sway as a key name
+mod modifier_mask {
+    use super::Modifiers;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(m: &Modifiers, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(m.bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Modifiers, D::Error> {
+        match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::Number(n) => n
+                .as_u64()
+                .and_then(|n| u32::try_from(n).ok())
+                .map(Modifiers::from_bits_truncate)
+                .ok_or_else(|| de::Error::custom(format!("invalid modifier mask {n}"))),
+            serde_json::Value::String(s) => Ok(s
+                .split('+')
+                .filter_map(Modifiers::from_name)
+                .fold(Modifiers::empty(), |acc, m| acc | m)),
+            serde_json::Value::Null => Ok(Modifiers::empty()),
+            other => Err(de::Error::custom(format!("invalid modifier {other}"))),
+        }
+    }
+}
+
+/// A color from the bar config, parsed from `#rrggbb` or `#rrggbbaa`
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// 255 when the color had no alpha component
+    pub a: u8,
+}
+
+impl Rgba {
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+impl FromStr for Rgba {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("invalid color {s:?}"));
+        let hex = s.strip_prefix('#').ok_or_else(invalid)?;
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        Ok(Rgba {
+            r: byte(0)?,
+            g: byte(2)?,
+            b: byte(4)?,
+            a: if hex.len() == 8 { byte(6)? } else { 255 },
+        })
+    }
+}
+
+impl fmt::Display for Rgba {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)?;
+        if self.a != 255 {
+            write!(f, "{:02x}", self.a)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Rgba {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rgba {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+string_enum! {
+    pub enum BarPart {
+        Background = "background",
+        Statusline = "statusline",
+        Separator = "separator",
+        FocusedBackground = "focused_background",
+        FocusedStatusline = "focused_statusline",
+        FocusedSeparator = "focused_separator",
+        FocusedWorkspaceText = "focused_workspace_text",
+        FocusedWorkspaceBg = "focused_workspace_bg",
+        FocusedWorkspaceBorder = "focused_workspace_border",
+        ActiveWorkspaceText = "active_workspace_text",
+        ActiveWorkspaceBg = "active_workspace_bg",
+        ActiveWorkspaceBorder = "active_workspace_border",
+        InactiveWorkspaceText = "inactive_workspace_text",
+        InactiveWorkspaceBg = "inactive_workspace_bg",
+        InactiveWorkspaceBorder = "inactive_workspace_border",
+        UrgentWorkspaceText = "urgent_workspace_text",
+        UrgentWorkspaceBg = "urgent_workspace_bg",
+        UrgentWorkspaceBorder = "urgent_workspace_border",
+        BindingModeText = "binding_mode_text",
+        BindingModeBg = "binding_mode_bg",
+        BindingModeBorder = "binding_mode_border",
+    }
 }
 
 /// Version reply
@@ -456,6 +789,118 @@ mod tests {
         assert!(o.is_ok());
     }
 
+    #[test]
+    fn test_window_properties_wrong_type() {
+        let ok = r#"{"class":"Firefox","transient_for":null}"#;
+        let props: WindowProperties = serde_json::from_str(ok).unwrap();
+        assert_eq!(props.class.as_deref(), Some("Firefox"));
+
+        let bad = r#"{"class":42}"#;
+        assert!(serde_json::from_str::<WindowProperties>(bad).is_err());
+        let bad = r#"{"transient_for":"nope"}"#;
+        assert!(serde_json::from_str::<WindowProperties>(bad).is_err());
+    }
+
+    #[test]
+    fn test_unknown_values() {
+        let output = r#"{"name":"HDMI-1","active":true,"primary":false,"rect":{"x":0,"y":0,"width":1920,"height":1080},"current_workspace":"1","make":"Acme","scale":2}"#;
+        let o: Output = serde_json::from_str(output).unwrap();
+        assert_eq!(o.extra["make"], "Acme");
+        assert_eq!(o.extra["scale"], 2);
+        let round_trip: Output = serde_json::from_str(&serde_json::to_string(&o).unwrap()).unwrap();
+        assert_eq!(round_trip, o);
+
+        assert_eq!(
+            serde_json::from_str::<NodeLayout>(r#""splith""#).unwrap(),
+            NodeLayout::SplitH
+        );
+        let layout: NodeLayout = serde_json::from_str(r#""spiral""#).unwrap();
+        assert_eq!(layout, NodeLayout::Unknown("spiral".into()));
+        assert_eq!(serde_json::to_string(&layout).unwrap(), r#""spiral""#);
+
+        let mode: FullscreenMode = serde_json::from_str("7").unwrap();
+        assert_eq!(mode, FullscreenMode::Unknown(7));
+
+        let props: WindowProperties =
+            serde_json::from_str(r#"{"class":"URxvt","window_icon":[1,2,3]}"#).unwrap();
+        assert_eq!(props.class.as_deref(), Some("URxvt"));
+    }
+
+    #[test]
+    fn test_bar_config() {
+        let output = include_str!("../test/bar_config.json");
+        let bar: BarConfig = serde_json::from_str(output).unwrap();
+        assert_eq!(bar.mode, BarMode::Hide);
+        assert_eq!(bar.position, BarPosition::Top);
+        assert_eq!(bar.hidden_state, Some(HiddenState::Hide));
+        assert_eq!(bar.modifier, Modifiers::MOD4);
+        assert_eq!(bar.tray_output, vec!["primary".to_owned()]);
+        assert!(bar.strip_workspace_numbers);
+        assert_eq!(bar.bindings[0].input_code, 4);
+        assert_eq!(
+            bar.colors[&BarPart::Background],
+            Rgba::new(0x22, 0x22, 0x22, 0xff)
+        );
+        assert_eq!(
+            bar.colors[&BarPart::Statusline],
+            Rgba::new(0xdd, 0xdd, 0xdd, 0x80)
+        );
+        let round_trip: BarConfig =
+            serde_json::from_str(&serde_json::to_string(&bar).unwrap()).unwrap();
+        assert_eq!(round_trip, bar);
+    }
+
+    #[test]
+    fn test_rgba() {
+        assert_eq!(
+            "#ff0080".parse::<Rgba>().unwrap(),
+            Rgba::new(255, 0, 128, 255)
+        );
+        assert_eq!(
+            "#FF008040".parse::<Rgba>().unwrap(),
+            Rgba::new(255, 0, 128, 64)
+        );
+        assert_eq!(Rgba::new(255, 0, 128, 255).to_string(), "#ff0080");
+        assert_eq!(Rgba::new(255, 0, 128, 64).to_string(), "#ff008040");
+        for bad in ["ff0080", "#ff00", "#ff00800", "#gg0080", "#ff00€"] {
+            assert!(bad.parse::<Rgba>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_rect() {
+        let a = Rect::new(0, 0, 100, 50);
+        let b = Rect::new(80, 40, 40, 40);
+        assert_eq!(a.area(), 5000);
+        assert_eq!(a.center(), (50, 25));
+        assert!(a.contains_point(99, 49));
+        assert!(!a.contains_point(100, 0));
+        assert!(a.contains(&Rect::new(10, 10, 90, 40)));
+        assert!(!a.contains(&b));
+        assert_eq!(a.intersection(&b), Some(Rect::new(80, 40, 20, 10)));
+        assert_eq!(a.intersection(&Rect::new(100, 0, 5, 5)), None);
+        assert_eq!(a.union(&b), Rect::new(0, 0, 120, 80));
+        assert_eq!(b.translate(-80, 10), Rect::new(0, 50, 40, 40));
+        assert_eq!(b.clamp_to(&a), Rect::new(60, 10, 40, 40));
+        assert_eq!(
+            Rect::new(0, 0, 500, 20).clamp_to(&a),
+            Rect::new(0, 0, 100, 20)
+        );
+        assert_eq!(
+            Rect::new(10, 5, 80, 40).edge_distances(&a),
+            Edges {
+                top: 5,
+                right: 10,
+                bottom: 5,
+                left: 10
+            }
+        );
+        let inner = Rect::new(2, 20, 10, 10);
+        let container = Rect::new(100, 200, 50, 50);
+        assert_eq!(inner.to_absolute(&container), Rect::new(102, 220, 10, 10));
+        assert_eq!(inner.to_absolute(&container).to_relative(&container), inner);
+    }
+
     #[test]
     fn test_version() {
         let output = include_str!("../test/version.json");
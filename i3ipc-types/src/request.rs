@@ -0,0 +1,337 @@
+//! Pairs every message sent to i3 with the type of its reply. Each client
+//! exposes a generic `request` method, so the `Msg` sent and the reply type
+//! expected can never disagree:
+//!
+//! ```ignore
+//! let tree: reply::Node = i3.request(request::Tree).await?;
+//! let resp = i3.request(request::RunCommand("workspace 2".into())).await?;
+//! ```
+use serde::de::DeserializeOwned;
+
+use std::{
+    io, process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{event, frame, msg::Msg, reply};
+
+/// A message that can be sent to i3 along with the reply it produces
+pub trait Request {
+    /// The reply i3 responds with
+    type Reply: DeserializeOwned;
+
+    /// Message type sent to i3
+    const MSG: Msg;
+
+    /// Payload sent along with the message, if any
+    fn payload(&self) -> io::Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Encode the complete frame to write to i3
+    fn encode(&self) -> io::Result<Vec<u8>> {
+        Ok(frame::encode(
+            Self::MSG,
+            self.payload()?.as_ref().map(|p| p.as_bytes()),
+        ))
+    }
+
+    /// Deserialize the reply, checking that it answers this request
+    fn decode_reply(msg_type: u32, payload: &[u8]) -> io::Result<Self::Reply> {
+        if msg_type != u32::from(Self::MSG) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Expected reply to {:?} ({}) but received message type {}",
+                    Self::MSG,
+                    u32::from(Self::MSG),
+                    msg_type
+                ),
+            ));
+        }
+        Ok(serde_json::from_slice(payload)?)
+    }
+}
+
+/// Run one or more commands, see
+/// [RunCommand](../msg/enum.Msg.html#variant.RunCommand)
+#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+pub struct RunCommand(pub String);
+
+impl Request for RunCommand {
+    type Reply = Vec<reply::Success>;
+    const MSG: Msg = Msg::RunCommand;
+
+    fn payload(&self) -> io::Result<Option<String>> {
+        Ok(Some(self.0.clone()))
+    }
+}
+
+/// Get the list of current workspaces
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct Workspaces;
+
+impl Request for Workspaces {
+    type Reply = reply::Workspaces;
+    const MSG: Msg = Msg::Workspaces;
+}
+
+/// Subscribe this connection to the listed events
+#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+pub struct Subscribe(pub Vec<event::Subscribe>);
+
+impl Request for Subscribe {
+    type Reply = reply::Success;
+    const MSG: Msg = Msg::Subscribe;
+
+    fn payload(&self) -> io::Result<Option<String>> {
+        Ok(Some(serde_json::to_string(&self.0)?))
+    }
+}
+
+/// Get the list of current outputs
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct Outputs;
+
+impl Request for Outputs {
+    type Reply = reply::Outputs;
+    const MSG: Msg = Msg::Outputs;
+}
+
+/// Get the layout tree
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct Tree;
+
+impl Request for Tree {
+    type Reply = reply::Node;
+    const MSG: Msg = Msg::Tree;
+}
+
+/// Get the names of all currently set marks
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct Marks;
+
+impl Request for Marks {
+    type Reply = reply::Marks;
+    const MSG: Msg = Msg::Marks;
+}
+
+/// Get the ids of all configured bars
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct BarIds;
+
+impl Request for BarIds {
+    type Reply = reply::BarIds;
+    const MSG: Msg = Msg::BarConfig;
+}
+
+/// Get the configuration of the bar with the given id
+#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+pub struct BarConfig(pub String);
+
+impl Request for BarConfig {
+    type Reply = reply::BarConfig;
+    const MSG: Msg = Msg::BarConfig;
+
+    fn payload(&self) -> io::Result<Option<String>> {
+        Ok(Some(self.0.clone()))
+    }
+}
+
+/// Get the i3 version
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct Version;
+
+impl Request for Version {
+    type Reply = reply::Version;
+    const MSG: Msg = Msg::Version;
+}
+
+/// Get the names of all configured binding modes
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct BindingModes;
+
+impl Request for BindingModes {
+    type Reply = reply::BindingModes;
+    const MSG: Msg = Msg::BindingModes;
+}
+
+/// Get the last loaded config
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct Config;
+
+impl Request for Config {
+    type Reply = reply::Config;
+    const MSG: Msg = Msg::Config;
+}
+
+/// Send a tick event with the given payload to all subscribers
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Default)]
+pub struct Tick(pub String);
+
+impl Tick {
+    /// A tick whose payload no other tick sent from this process will share,
+    /// used by [Barrier](struct.Barrier.html) to find its own tick in the
+    /// event stream
+    pub fn unique() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        Tick(format!(
+            "i3ipc-barrier-{}-{}",
+            process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+}
+
+impl Request for Tick {
+    type Reply = reply::Success;
+    const MSG: Msg = Msg::Tick;
+
+    fn payload(&self) -> io::Result<Option<String>> {
+        Ok(Some(self.0.clone()))
+    }
+}
+
+/// The part of the clients' `barrier` shared between them: send
+/// [tick](struct.Barrier.html#method.tick), [check](struct.Barrier.html#method.check)
+/// i3's reply, then [push](struct.Barrier.html#method.push) events until it
+/// returns true
+#[derive(Debug)]
+pub struct Barrier {
+    tick: Tick,
+    seen: Vec<event::Event>,
+}
+
+impl Default for Barrier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Barrier {
+    pub fn new() -> Self {
+        Barrier {
+            tick: Tick::unique(),
+            seen: Vec::new(),
+        }
+    }
+
+    /// The tick to send
+    pub fn tick(&self) -> Tick {
+        self.tick.clone()
+    }
+
+    /// A tick i3 didn't accept never arrives, so waiting for it would never
+    /// end; that is an `InvalidData` error
+    pub fn check(&self, reply: &reply::Success) -> io::Result<()> {
+        if reply.success {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            reply
+                .error
+                .clone()
+                .unwrap_or_else(|| "i3 rejected the barrier tick".to_owned()),
+        ))
+    }
+
+    /// Record an event, returning true if it is the barrier's own tick
+    pub fn push(&mut self, evt: event::Event) -> bool {
+        match evt {
+            event::Event::Tick(t) if t.payload == self.tick.0 => true,
+            evt => {
+                self.seen.push(evt);
+                false
+            }
+        }
+    }
+
+    /// Events received before the tick, in order
+    pub fn into_events(self) -> Vec<event::Event> {
+        self.seen
+    }
+
+    /// The error to return if the event stream ends before the tick arrives
+    pub fn closed() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "event stream closed before barrier tick arrived",
+        )
+    }
+}
+
+/// Sends [Sync](../msg/enum.Msg.html#variant.Sync)
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct Sync;
+
+impl Request for Sync {
+    type Reply = reply::Success;
+    const MSG: Msg = Msg::Sync;
+}
+
+/// Get the currently active binding mode
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub struct BindingState;
+
+impl Request for BindingState {
+    type Reply = reply::BindingState;
+    const MSG: Msg = Msg::BindingState;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_tick() {
+        let (a, b) = (Tick::unique(), Tick::unique());
+        assert_ne!(a, b);
+        assert!(a.0.starts_with("i3ipc-barrier-"));
+    }
+
+    #[test]
+    fn test_barrier() {
+        let tick = |payload: &str| {
+            event::Event::Tick(
+                serde_json::from_value(serde_json::json!({"first": false, "payload": payload}))
+                    .unwrap(),
+            )
+        };
+        let mut barrier = Barrier::new();
+        let payload = barrier.tick().0;
+        assert!(!barrier.push(tick("other")));
+        assert!(barrier.push(tick(&payload)));
+        assert_eq!(barrier.into_events().len(), 1);
+
+        let ok: reply::Success = serde_json::from_str(r#"{"success":true}"#).unwrap();
+        let failed: reply::Success = serde_json::from_str(r#"{"success":false}"#).unwrap();
+        let barrier = Barrier::new();
+        assert!(barrier.check(&ok).is_ok());
+        assert_eq!(
+            barrier.check(&failed).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_subscribe_payload() {
+        let req = Subscribe(vec![
+            event::Subscribe::Window,
+            event::Subscribe::BarConfigUpdate,
+        ]);
+        assert_eq!(
+            req.payload().unwrap().unwrap(),
+            r#"["window","barconfig_update"]"#
+        );
+    }
+
+    #[test]
+    fn test_reply_type_checked() {
+        let ok = BindingState::decode_reply(12, br#"{"name":"default"}"#).unwrap();
+        assert_eq!(ok.name, "default");
+
+        let err = BindingState::decode_reply(8, br#"["default"]"#).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,226 @@
+//! A snapshot of every workspace: where it lives, its layout (including
+//! floating geometry and marks) and the commands that relaunch its windows.
+//! Restoring appends each workspace's [Layout](../layout/struct.Layout.html)
+//! and execs the commands, whose windows are swallowed into the
+//! placeholders.
+//!
+//! Restoring relies on `append_layout` and on swallowing by X11 window
+//! properties, so sessions are i3 only; sway has neither.
+//!
+//! i3 doesn't report which command started a window, so capturing takes a
+//! resolver from window to command, usually a lookup by class. Given a
+//! window's pid, e.g. from its `_NET_WM_PID` property,
+//! [proc_cmdline](fn.proc_cmdline.html) reads the command line instead.
+use serde::{Deserialize, Serialize};
+
+use std::{fs, io, path::Path};
+
+use crate::{
+    command::{focus_workspace, quote, Command},
+    layout::{Layout, LayoutNode, SwallowProps},
+    reply::{Node, NodeType, Output, Workspace},
+};
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+pub struct Session {
+    pub workspaces: Vec<WorkspaceSession>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct WorkspaceSession {
+    pub name: String,
+    pub output: String,
+    pub visible: bool,
+    pub focused: bool,
+    pub layout: Vec<LayoutNode>,
+    /// Commands relaunching the workspace's windows, one per window
+    #[serde(default)]
+    pub execs: Vec<String>,
+}
+
+impl Session {
+    /// Snapshot `workspaces` from `tree`, asking `resolve` for the command that
+    /// relaunches each window. Windows it returns `None` for are left as
+    /// empty placeholders.
+    pub fn capture<F>(
+        tree: &Node,
+        workspaces: &[Workspace],
+        props: &SwallowProps,
+        resolve: F,
+    ) -> Self
+    where
+        F: Fn(&Node) -> Option<String>,
+    {
+        let workspaces = workspaces
+            .iter()
+            .filter_map(|ws| {
+                let node = tree.find_workspace(&ws.name)?;
+                let mut execs = Vec::new();
+                windows(node, &mut |win| execs.extend(resolve(win)));
+                Some(WorkspaceSession {
+                    name: ws.name.clone(),
+                    output: ws.output.clone(),
+                    visible: ws.visible,
+                    focused: ws.focused,
+                    layout: Layout::from_workspace(node, props).nodes,
+                    execs,
+                })
+            })
+            .collect();
+        Session { workspaces }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Commands bringing back the visible and focused workspaces, to run once
+    /// every workspace has been restored
+    pub fn focus_commands(&self) -> Vec<Command> {
+        let visible = self
+            .workspaces
+            .iter()
+            .filter(|ws| ws.visible && !ws.focused);
+        let focused = self.workspaces.iter().filter(|ws| ws.focused);
+        visible
+            .chain(focused)
+            .map(|ws| focus_workspace(&ws.name))
+            .collect()
+    }
+}
+
+impl WorkspaceSession {
+    pub fn layout(&self) -> Layout {
+        Layout {
+            nodes: self.layout.clone(),
+        }
+    }
+
+    /// Commands recreating this workspace, given the path its layout has been
+    /// written to. It's moved to its output if that is among the active
+    /// `outputs`, otherwise it stays on the focused one.
+    pub fn restore_commands(&self, layout_path: &str, outputs: &[Output]) -> Vec<Command> {
+        let mut cmds = vec![focus_workspace(&self.name)];
+        if outputs.iter().any(|o| o.active && o.name == self.output) {
+            cmds.push(Command::new(format!(
+                "move workspace to output {}",
+                quote(&self.output)
+            )));
+        }
+        if !self.layout.is_empty() {
+            cmds.push(Command::new(format!(
+                "append_layout {}",
+                quote(layout_path)
+            )));
+        }
+        cmds.extend(
+            self.execs
+                .iter()
+                .map(|e| Command::new(format!("exec --no-startup-id {}", quote(e)))),
+        );
+        cmds
+    }
+}
+
+/// Command line of process `pid`, read from `/proc` and quoted for a shell.
+/// i3 doesn't report window pids, the caller has to find them.
+pub fn proc_cmdline(pid: u32) -> Option<String> {
+    let raw = fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    let args: Vec<String> = raw
+        .split(|b| *b == 0)
+        .filter(|a| !a.is_empty())
+        .map(|a| shell_quote(&String::from_utf8_lossy(a)))
+        .collect();
+    (!args.is_empty()).then(|| args.join(" "))
+}
+
+fn shell_quote(arg: &str) -> String {
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c))
+    {
+        arg.to_owned()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+fn windows(node: &Node, f: &mut dyn FnMut(&Node)) {
+    if node.window.is_some() && node.node_type == NodeType::Con {
+        f(node);
+    }
+    for n in node.nodes.iter().chain(&node.floating_nodes) {
+        windows(n, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_and_restore() {
+        let tree: Node = serde_json::from_str(include_str!("../test/tree.json")).unwrap();
+        let ws_node = tree
+            .find(|n| n.node_type == NodeType::Workspace && !n.nodes.is_empty())
+            .unwrap();
+        let workspaces: Vec<Workspace> = serde_json::from_value(serde_json::json!([{
+            "id": ws_node.id,
+            "num": ws_node.num.unwrap_or(-1),
+            "name": ws_node.name,
+            "visible": true,
+            "focused": true,
+            "urgent": false,
+            "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+            "output": "DP-1",
+            "focus": [],
+        }]))
+        .unwrap();
+        let session = Session::capture(&tree, &workspaces, &SwallowProps::default(), |_| {
+            Some("xterm -e 'top'".to_owned())
+        });
+        assert_eq!(session.workspaces.len(), 1);
+        let ws = &session.workspaces[0];
+        assert_eq!(ws.output, "DP-1");
+        assert!(!ws.execs.is_empty());
+
+        let json = serde_json::to_string(&session).unwrap();
+        assert_eq!(serde_json::from_str::<Session>(&json).unwrap(), session);
+
+        let outputs: Vec<Output> = serde_json::from_value(serde_json::json!([{
+            "name": "DP-1",
+            "active": true,
+            "primary": true,
+            "rect": {"x": 0, "y": 0, "width": 1920, "height": 1080},
+        }]))
+        .unwrap();
+        let cmds = ws.restore_commands("/tmp/ws.json", &outputs);
+        assert_eq!(cmds[1].action, r#"move workspace to output "DP-1""#);
+        assert_eq!(cmds[2].action, r#"append_layout "/tmp/ws.json""#);
+        assert_eq!(cmds[3].action, r#"exec --no-startup-id "xterm -e 'top'""#);
+        assert_eq!(session.focus_commands().len(), 1);
+
+        // with DP-1 gone the workspace stays where i3 creates it
+        let cmds = ws.restore_commands("/tmp/ws.json", &[]);
+        assert_eq!(cmds[1].action, r#"append_layout "/tmp/ws.json""#);
+    }
+
+    #[test]
+    fn test_proc_cmdline() {
+        let own = proc_cmdline(std::process::id()).unwrap();
+        let exe = std::env::args().next().unwrap();
+        assert!(own.starts_with(&shell_quote(&exe)));
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("--class=foo"), "--class=foo");
+        assert_eq!(shell_quote("it's here"), r"'it'\''s here'");
+        assert_eq!(shell_quote(""), "''");
+    }
+}
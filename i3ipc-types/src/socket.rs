@@ -0,0 +1,177 @@
+//! Locating the IPC socket of a running i3 (or sway) instance without
+//! necessarily spawning the `i3` binary.
+//!
+//! [find](fn.find.html) searches in the following order and returns the first
+//! match:
+//!
+//! 1. the `I3SOCK` environment variable
+//! 2. the `SWAYSOCK` environment variable
+//! 3. live sockets matching `$XDG_RUNTIME_DIR/i3/ipc-socket.*`
+//! 4. live sockets matching `/run/user/$UID/sway-ipc.*`
+//! 5. the output of `i3 --get-socketpath`
+//!
+//! Paths taken from the environment are trusted as-is, directory scans only
+//! yield sockets that accept a connection. When several window manager
+//! instances are running, [find_all](fn.find_all.html) lists every live socket
+//! it can see.
+use std::{
+    env, fs, io,
+    os::unix::{fs::FileTypeExt, net::UnixStream},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Where a socket path was discovered
+#[derive(Eq, PartialEq, Hash, Debug, Clone, Copy)]
+pub enum Source {
+    /// `I3SOCK` environment variable
+    I3Sock,
+    /// `SWAYSOCK` environment variable
+    SwaySock,
+    /// `$XDG_RUNTIME_DIR/i3/ipc-socket.*`
+    I3RuntimeDir,
+    /// `/run/user/$UID/sway-ipc.*`
+    SwayRuntimeDir,
+    /// `i3 --get-socketpath`
+    I3Binary,
+}
+
+/// A discovered socket path along with the place it was found
+#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+pub struct SocketPath {
+    pub path: PathBuf,
+    pub source: Source,
+}
+
+/// Find the socket to connect to, following the documented search order
+pub fn find() -> io::Result<SocketPath> {
+    if let Some(p) = from_env("I3SOCK", Source::I3Sock) {
+        return Ok(p);
+    }
+    if let Some(p) = from_env("SWAYSOCK", Source::SwaySock) {
+        return Ok(p);
+    }
+    if let Some(p) = scan_runtime_dirs().into_iter().next() {
+        return Ok(p);
+    }
+    from_binary()
+}
+
+/// List every live socket that can be discovered, in search order. The `i3`
+/// binary is not consulted; any socket it reports lives in one of the scanned
+/// directories or was passed through `I3SOCK`.
+pub fn find_all() -> Vec<SocketPath> {
+    let mut found: Vec<SocketPath> = Vec::new();
+    let env = [
+        from_env("I3SOCK", Source::I3Sock),
+        from_env("SWAYSOCK", Source::SwaySock),
+    ];
+    for sock in env.into_iter().flatten().chain(scan_runtime_dirs()) {
+        if is_live(&sock.path) && !found.iter().any(|f| f.path == sock.path) {
+            found.push(sock);
+        }
+    }
+    found
+}
+
+/// Returns true if something is accepting connections at `path`
+pub fn is_live<P: AsRef<Path>>(path: P) -> bool {
+    UnixStream::connect(path).is_ok()
+}
+
+fn from_env(var: &str, source: Source) -> Option<SocketPath> {
+    env::var_os(var)
+        .filter(|p| !p.is_empty())
+        .map(|p| SocketPath {
+            path: PathBuf::from(p),
+            source,
+        })
+}
+
+fn from_binary() -> io::Result<SocketPath> {
+    let out = Command::new("i3").arg("--get-socketpath").output()?;
+    if out.status.success() {
+        Ok(SocketPath {
+            path: PathBuf::from(String::from_utf8_lossy(&out.stdout).trim_end()),
+            source: Source::I3Binary,
+        })
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::BrokenPipe,
+            "Unable to get i3 socket path",
+        ))
+    }
+}
+
+fn scan_runtime_dirs() -> Vec<SocketPath> {
+    let mut found = Vec::new();
+    if let Some(dir) = env::var_os("XDG_RUNTIME_DIR").filter(|d| !d.is_empty()) {
+        found.extend(scan(
+            Path::new(&dir).join("i3"),
+            "ipc-socket.",
+            Source::I3RuntimeDir,
+        ));
+    }
+    // SAFETY: getuid has no preconditions and cannot fail
+    let uid = unsafe { libc::getuid() };
+    found.extend(scan(
+        PathBuf::from(format!("/run/user/{uid}")),
+        "sway-ipc.",
+        Source::SwayRuntimeDir,
+    ));
+    found
+}
+
+/// Live sockets in `dir` whose file name starts with `prefix`, sorted by path
+fn scan<P: AsRef<Path>>(dir: P, prefix: &str, source: Source) -> Vec<SocketPath> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut paths = entries
+        .filter_map(Result::ok)
+        .filter(|e| e.file_type().map(|t| t.is_socket()).unwrap_or(false))
+        .filter(|e| e.file_name().to_string_lossy().starts_with(prefix))
+        .map(|e| e.path())
+        .filter(|p| is_live(p))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| SocketPath { path, source })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    fn test_scan_live_sockets() {
+        let dir = env::temp_dir().join(format!("i3ipc-socket-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let _live = UnixListener::bind(dir.join("ipc-socket.1")).unwrap();
+        // bound then dropped, leaves a stale socket file behind
+        drop(UnixListener::bind(dir.join("ipc-socket.2")).unwrap());
+        let _other = UnixListener::bind(dir.join("sway-ipc.1000.1.sock")).unwrap();
+        fs::write(dir.join("ipc-socket.txt"), b"not a socket").unwrap();
+
+        let found = scan(&dir, "ipc-socket.", Source::I3RuntimeDir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            found,
+            vec![SocketPath {
+                path: dir.join("ipc-socket.1"),
+                source: Source::I3RuntimeDir,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_scan_missing_dir() {
+        assert!(scan("/nonexistent/i3ipc", "ipc-socket.", Source::I3RuntimeDir).is_empty());
+    }
+}
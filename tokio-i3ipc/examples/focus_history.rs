@@ -0,0 +1,47 @@
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use tokio_i3ipc::{
+    event::Subscribe,
+    focus_history::{FocusHistory, HistoryQuery},
+    focus_service, I3,
+};
+
+// Run with no arguments to track focus, then bind a key to
+// `focus_history previous` to switch to the last focused window
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> io::Result<()> {
+    let path = focus_service::default_socket_path()?;
+    if let Some(arg) = std::env::args().nth(1) {
+        let query: HistoryQuery = arg.parse()?;
+        let ids = focus_service::query(&path, &query).await?;
+        if query == HistoryQuery::Previous {
+            if let Some(id) = ids.first() {
+                I3::connect()
+                    .await?
+                    .run_command(format!("[con_id={id}] focus"))
+                    .await?;
+            }
+        } else {
+            println!("{:?}", ids);
+        }
+        return Ok(());
+    }
+
+    let history = Arc::new(Mutex::new(FocusHistory::new()));
+    let mut events = I3::connect().await?;
+    events
+        .subscribe([Subscribe::Window, Subscribe::Workspace])
+        .await?;
+    let mut events = events.listen();
+    tokio::spawn(focus_service::serve(
+        focus_service::bind(&path)?,
+        history.clone(),
+    ));
+    I3::connect()
+        .await?
+        .run_focus_history(&history, &mut events)
+        .await
+}
@@ -0,0 +1,146 @@
+//! Picking the split direction for the next window from the shape of the
+//! focused one, so windows alternate between side by side and stacked as
+//! they get smaller. Wider than tall splits horizontally, otherwise
+//! vertically.
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeSet;
+
+use crate::{
+    command::Command,
+    criteria::Criteria,
+    neighbor::path_to,
+    reply::{FullscreenMode, Node, NodeLayout, NodeType},
+};
+
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Autotiler {
+    /// Leave containers nested deeper than this below their workspace alone,
+    /// a window directly on a workspace being at depth 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth_limit: Option<usize>,
+    /// Only tile these workspaces. Empty means all of them.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub workspaces: BTreeSet<String>,
+    /// Never tile these workspaces
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub disabled: BTreeSet<String>,
+    /// Windows matching any of these are left alone
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<Criteria>,
+}
+
+impl Autotiler {
+    pub fn is_enabled(&self, workspace: &str) -> bool {
+        !self.disabled.contains(workspace)
+            && (self.workspaces.is_empty() || self.workspaces.contains(workspace))
+    }
+
+    pub fn enable(&mut self, workspace: &str) {
+        self.disabled.remove(workspace);
+        if !self.workspaces.is_empty() {
+            self.workspaces.insert(workspace.to_owned());
+        }
+    }
+
+    pub fn disable(&mut self, workspace: &str) {
+        self.disabled.insert(workspace.to_owned());
+    }
+
+    /// The split command for container `id` in `tree`, usually the one just
+    /// focused. `None` if it should be left alone: it is floating,
+    /// fullscreen, tabbed or stacked, excluded, too deep, on a disabled
+    /// workspace, or already split the right way.
+    pub fn split_for(&self, tree: &Node, id: usize) -> Option<Command> {
+        let path = path_to(tree, id)?;
+        let ws_idx = path
+            .iter()
+            .position(|n| n.node_type == NodeType::Workspace)?;
+        let (con, parent) = match path[ws_idx + 1..] {
+            [.., parent, con] => (con, parent),
+            [con] => (con, path[ws_idx]),
+            [] => return None,
+        };
+        let depth = path.len() - ws_idx - 1;
+        if self.depth_limit.is_some_and(|limit| depth > limit)
+            || !self.is_enabled(path[ws_idx].name.as_deref()?)
+            || path.iter().any(|n| n.node_type == NodeType::FloatingCon)
+            || con.fullscreen_mode != FullscreenMode::None
+            || matches!(parent.layout, NodeLayout::Tabbed | NodeLayout::Stacked)
+            || self.exclude.iter().any(|c| c.matches(con))
+        {
+            return None;
+        }
+        let layout = if con.rect.height > con.rect.width {
+            NodeLayout::SplitV
+        } else {
+            NodeLayout::SplitH
+        };
+        if parent.layout == layout {
+            return None;
+        }
+        let split = match layout {
+            NodeLayout::SplitV => "split v",
+            _ => "split h",
+        };
+        Some(Command::with_criteria(Criteria::con_id(con.id), split))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // workspace 7: [ VSCODE | [ GHCI / SHELL ] ]
+    const VSCODE: usize = 106996225509760;
+    const GHCI: usize = 106996225511040;
+    const PIDGIN_STACKED: usize = 106996225537920;
+    const POLYBAR: usize = 106996225536000;
+
+    fn tree() -> Node {
+        serde_json::from_str(include_str!("../../i3ipc-types/test/tree.json")).unwrap()
+    }
+
+    fn split(tiler: &Autotiler, id: usize) -> Option<String> {
+        tiler.split_for(&tree(), id).map(|c| c.to_string())
+    }
+
+    #[test]
+    fn test_aspect_ratio() {
+        let tiler = Autotiler::default();
+        // tall
+        assert_eq!(
+            split(&tiler, VSCODE).as_deref(),
+            Some(&*format!("[con_id={VSCODE}] split v"))
+        );
+        // wide
+        assert_eq!(
+            split(&tiler, GHCI).as_deref(),
+            Some(&*format!("[con_id={GHCI}] split h"))
+        );
+        assert_eq!(split(&tiler, PIDGIN_STACKED), None);
+        // not on a workspace
+        assert_eq!(split(&tiler, POLYBAR), None);
+    }
+
+    #[test]
+    fn test_limits() {
+        let tiler = Autotiler {
+            depth_limit: Some(1),
+            ..Autotiler::default()
+        };
+        assert!(split(&tiler, VSCODE).is_some());
+        assert_eq!(split(&tiler, GHCI), None);
+
+        let mut tiler = Autotiler {
+            exclude: vec![Criteria::class("URxvt")],
+            ..Autotiler::default()
+        };
+        assert_eq!(split(&tiler, GHCI), None);
+        tiler.disable("7");
+        assert_eq!(split(&tiler, VSCODE), None);
+        tiler.enable("7");
+        assert!(split(&tiler, VSCODE).is_some());
+    }
+}
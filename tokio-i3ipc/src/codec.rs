@@ -23,7 +23,7 @@
 use bytes::{buf::Buf, BytesMut};
 use tokio_util::codec::Decoder;
 
-use i3ipc_types::{decode_event, event, MAGIC};
+use i3ipc_types::{decode_event, event, frame};
 
 use std::io;
 
@@ -31,33 +31,39 @@ use std::io;
 /// from i3 and turn them into frames of Events. All other interactions with i3
 /// over the IPC are simple send/receive operations. Events received will be
 /// relative to what was subscribed.
-pub struct EventCodec;
+#[derive(Debug, Clone, Copy)]
+pub struct EventCodec {
+    max_frame_len: usize,
+}
+
+impl EventCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject events with payloads over `len` bytes
+    pub fn with_max_frame_len(len: usize) -> Self {
+        EventCodec { max_frame_len: len }
+    }
+}
+
+impl Default for EventCodec {
+    fn default() -> Self {
+        Self::with_max_frame_len(frame::DEFAULT_MAX_FRAME_LEN)
+    }
+}
 
 impl Decoder for EventCodec {
     type Error = io::Error;
     type Item = event::Event;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, io::Error> {
-        if src.len() > 14 {
-            if &src[0..6] != MAGIC.as_bytes() {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Expected 'i3-ipc' but received: {:?}", &src[0..6]),
-                ));
-            }
-            let payload_len = u32::from_ne_bytes([src[6], src[7], src[8], src[9]]) as usize;
-            let evt_type = u32::from_ne_bytes([src[10], src[11], src[12], src[13]]);
-            // ends at payload + original 14 bytes
-            let end_len = 14 + payload_len;
-            if src.len() < end_len {
-                Ok(None)
-            } else {
-                let evt = decode_event(evt_type, &src[14..end_len])?;
-                src.advance(end_len);
-                Ok(Some(evt))
+        match frame::decode_with_limit(src, self.max_frame_len)? {
+            Some((frame, len)) => {
+                src.advance(len);
+                decode_event(frame.msg_type, frame.payload).map(Some)
             }
-        } else {
-            Ok(None)
+            None => Ok(None),
         }
     }
 }
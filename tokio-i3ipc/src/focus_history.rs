@@ -0,0 +1,251 @@
+//! Most recently used order of windows, for the "focus last window" i3
+//! doesn't have. A [FocusHistory](struct.FocusHistory.html) is seeded from
+//! the tree's focus stacks and kept up to date from window and workspace
+//! events. [HistoryQuery](enum.HistoryQuery.html) is the line protocol used to
+//! ask a running tracker for it.
+use std::{collections::HashMap, fmt, io, str::FromStr};
+
+use crate::{
+    event::{Event, WindowChange, WorkspaceChange},
+    reply::{Node, NodeType},
+};
+
+/// Container ids, most recently focused first
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+pub struct FocusHistory {
+    global: Vec<usize>,
+    workspaces: HashMap<String, Vec<usize>>,
+    current: Option<String>,
+}
+
+impl FocusHistory {
+    pub fn new() -> Self {
+        FocusHistory::default()
+    }
+
+    /// Order windows by the focus stacks in `tree`, which i3 keeps in most
+    /// recently used order for every container. The scratchpad is left out.
+    pub fn from_tree(tree: &Node) -> Self {
+        let mut history = FocusHistory::new();
+        focus_order(tree, None, &mut |ws, id| {
+            history.global.push(id);
+            history
+                .workspaces
+                .entry(ws.to_owned())
+                .or_default()
+                .push(id);
+        });
+        let mut node = tree;
+        while node.node_type != NodeType::Workspace {
+            match node.focus.first().and_then(|id| child(node, *id)) {
+                Some(next) => node = next,
+                None => return history,
+            }
+        }
+        history.current = node.name.clone();
+        history
+    }
+
+    /// Update from an event. Needs `Subscribe::Window` and
+    /// `Subscribe::Workspace`, the latter so focused windows can be put
+    /// under the right workspace.
+    pub fn handle(&mut self, event: &Event) {
+        match event {
+            Event::Workspace(ws) if ws.change == WorkspaceChange::Focus => {
+                self.current = ws.current.as_ref().and_then(|n| n.name.clone());
+            }
+            Event::Window(win) => match win.change {
+                WindowChange::Focus => self.focus(win.container.id),
+                WindowChange::Close => self.remove(win.container.id),
+                // the event doesn't say where it went, it's put back under
+                // a workspace when next focused
+                WindowChange::Move => {
+                    for ids in self.workspaces.values_mut() {
+                        ids.retain(|i| *i != win.container.id);
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    /// Record `id` as focused on the current workspace
+    pub fn focus(&mut self, id: usize) {
+        self.remove(id);
+        self.global.insert(0, id);
+        if let Some(ws) = &self.current {
+            self.workspaces.entry(ws.clone()).or_default().insert(0, id);
+        }
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        self.global.retain(|i| *i != id);
+        self.workspaces.retain(|_, ids| {
+            ids.retain(|i| *i != id);
+            !ids.is_empty()
+        });
+    }
+
+    pub fn global(&self) -> &[usize] {
+        &self.global
+    }
+
+    pub fn workspace(&self, name: &str) -> &[usize] {
+        self.workspaces.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Name of the focused workspace, if known
+    pub fn current_workspace(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// The window focused before the current one, for alt-tab
+    pub fn previous(&self) -> Option<usize> {
+        self.global.get(1).copied()
+    }
+
+    pub fn query(&self, query: &HistoryQuery) -> Vec<usize> {
+        match query {
+            HistoryQuery::Global => self.global.clone(),
+            HistoryQuery::Previous => self.previous().into_iter().collect(),
+            HistoryQuery::Workspace(name) => self.workspace(name).to_vec(),
+            HistoryQuery::Current => self
+                .current_workspace()
+                .map(|ws| self.workspace(ws).to_vec())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// A request to a running tracker, one per line. The reply is a JSON array of
+/// container ids on a single line.
+#[derive(Eq, PartialEq, Hash, Debug, Clone)]
+pub enum HistoryQuery {
+    /// `global`
+    Global,
+    /// `previous`, the window focused before the current one
+    Previous,
+    /// `current`, the history of the focused workspace
+    Current,
+    /// `workspace <name>`
+    Workspace(String),
+}
+
+impl FromStr for HistoryQuery {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().split_once(' ') {
+            Some(("workspace", name)) => Ok(HistoryQuery::Workspace(name.to_owned())),
+            None if s.trim() == "global" => Ok(HistoryQuery::Global),
+            None if s.trim() == "previous" => Ok(HistoryQuery::Previous),
+            None if s.trim() == "current" => Ok(HistoryQuery::Current),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown history query {:?}", s.trim()),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for HistoryQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HistoryQuery::Global => f.write_str("global"),
+            HistoryQuery::Previous => f.write_str("previous"),
+            HistoryQuery::Current => f.write_str("current"),
+            HistoryQuery::Workspace(name) => write!(f, "workspace {name}"),
+        }
+    }
+}
+
+fn child(node: &Node, id: usize) -> Option<&Node> {
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .find(|n| n.id == id)
+}
+
+/// Call `f` with each window under `node` and its workspace, most recently
+/// focused first
+fn focus_order(node: &Node, ws: Option<&str>, f: &mut dyn FnMut(&str, usize)) {
+    let ws = match node.node_type {
+        NodeType::Workspace => node.name.as_deref(),
+        _ => ws,
+    };
+    if ws == Some("__i3_scratch") {
+        return;
+    }
+    if let (Some(ws), Some(_)) = (ws, node.window) {
+        f(ws, node.id);
+    }
+    let focused = node.focus.iter().filter_map(|id| child(node, *id));
+    let rest = node
+        .nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .filter(|n| !node.focus.contains(&n.id));
+    for n in focused.chain(rest) {
+        focus_order(n, ws, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::WindowData;
+
+    fn window_event(change: WindowChange, container: &Node) -> Event {
+        Event::Window(Box::new(WindowData {
+            change,
+            container: container.clone(),
+        }))
+    }
+
+    #[test]
+    fn test_from_tree() {
+        let tree: Node =
+            serde_json::from_str(include_str!("../../i3ipc-types/test/tree.json")).unwrap();
+        let history = FocusHistory::from_tree(&tree);
+        let focused = tree.find(|n| n.focused).unwrap();
+        assert_eq!(history.global()[0], focused.id);
+        let ws = history.current_workspace().unwrap();
+        assert_eq!(history.workspace(ws)[0], focused.id);
+        assert!(history.global().len() > 1);
+    }
+
+    #[test]
+    fn test_events() {
+        let tree: Node =
+            serde_json::from_str(include_str!("../../i3ipc-types/test/tree.json")).unwrap();
+        let mut history = FocusHistory::from_tree(&tree);
+        let before = history.global().to_vec();
+        let other = tree
+            .find(|n| n.window.is_some() && n.id != before[0])
+            .unwrap();
+
+        history.handle(&window_event(WindowChange::Focus, other));
+        assert_eq!(history.global()[0], other.id);
+        assert_eq!(history.previous(), Some(before[0]));
+        assert_eq!(history.query(&HistoryQuery::Previous), vec![before[0]]);
+
+        history.handle(&window_event(WindowChange::Close, other));
+        let rest: Vec<usize> = before.into_iter().filter(|i| *i != other.id).collect();
+        assert_eq!(history.global(), &rest[..]);
+        assert!(!history.query(&HistoryQuery::Current).contains(&other.id));
+    }
+
+    #[test]
+    fn test_query() {
+        for q in [
+            HistoryQuery::Global,
+            HistoryQuery::Previous,
+            HistoryQuery::Current,
+            HistoryQuery::Workspace("2: web".into()),
+        ] {
+            assert_eq!(q.to_string().parse::<HistoryQuery>().unwrap(), q);
+        }
+        assert!("next".parse::<HistoryQuery>().is_err());
+    }
+}
@@ -0,0 +1,139 @@
+//! Answering queries about a
+//! [FocusHistory](../focus_history/struct.FocusHistory.html) kept up to date
+//! by [run_focus_history](../struct.I3.html#method.run_focus_history) on a
+//! local socket, so that a keybinding can focus the previous window through a
+//! small client:
+//!
+//! ```no_run
+//! # use std::io;
+//! use tokio_i3ipc::{focus_history::HistoryQuery, focus_service, I3};
+//!
+//! # async fn f() -> io::Result<()> {
+//! let path = focus_service::default_socket_path()?;
+//! if let Some(id) = focus_service::query(&path, &HistoryQuery::Previous).await?.first() {
+//!     I3::connect().await?.run_command(format!("[con_id={id}] focus")).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+
+use std::{
+    io,
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::focus_history::{FocusHistory, HistoryQuery};
+
+/// `$XDG_RUNTIME_DIR/i3ipc-focus-history.sock`. The runtime dir belongs to
+/// the user alone; a shared directory like the temp dir would let other
+/// users replace the socket, so `XDG_RUNTIME_DIR` being unset is a
+/// `NotFound` error.
+pub fn default_socket_path() -> io::Result<PathBuf> {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))?;
+    Ok(PathBuf::from(dir).join("i3ipc-focus-history.sock"))
+}
+
+/// Listen at `path`, replacing a socket left behind by an earlier run. Any
+/// other kind of file there is an `AlreadyExists` error.
+pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<UnixListener> {
+    let path = path.as_ref();
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ))
+        }
+        Err(_) => {}
+    }
+    UnixListener::bind(path)
+}
+
+/// Answer [HistoryQuery](../focus_history/enum.HistoryQuery.html) lines on
+/// `listener` until accepting a connection fails
+pub async fn serve(listener: UnixListener, history: Arc<Mutex<FocusHistory>>) -> io::Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let history = history.clone();
+        tokio::spawn(async move {
+            // a client hanging up early isn't the server's problem
+            let _ = answer(stream, &history).await;
+        });
+    }
+}
+
+/// Ask the server listening at `path`
+pub async fn query<P: AsRef<Path>>(path: P, query: &HistoryQuery) -> io::Result<Vec<usize>> {
+    let mut stream = UnixStream::connect(path).await?;
+    stream.write_all(format!("{query}\n").as_bytes()).await?;
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).await?;
+    if line.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "focus history server closed the connection",
+        ));
+    }
+    match serde_json::from_str(&line)? {
+        Reply::Ids(ids) => Ok(ids),
+        Reply::Error { error } => Err(io::Error::new(io::ErrorKind::InvalidInput, error)),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Reply {
+    Ids(Vec<usize>),
+    Error { error: String },
+}
+
+async fn answer(stream: UnixStream, history: &Mutex<FocusHistory>) -> io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    while let Some(line) = lines.next_line().await? {
+        let mut reply = match line.parse::<HistoryQuery>() {
+            Ok(q) => serde_json::to_string(&lock(history).query(&q))?,
+            Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+        };
+        reply.push('\n');
+        write.write_all(reply.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// The history stays usable if a holder of the lock panicked
+pub(crate) fn lock(history: &Mutex<FocusHistory>) -> std::sync::MutexGuard<'_, FocusHistory> {
+    history.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_serve_answers_queries() {
+        let tree = serde_json::from_str(include_str!("../../i3ipc-types/test/tree.json")).unwrap();
+        let history = FocusHistory::from_tree(&tree);
+        let expected = history.global().to_vec();
+        let history = Arc::new(Mutex::new(history));
+
+        let path = std::env::temp_dir().join(format!("focus-history-{}.sock", std::process::id()));
+        tokio::spawn(serve(bind(&path).unwrap(), history));
+
+        assert_eq!(query(&path, &HistoryQuery::Global).await.unwrap(), expected);
+        let previous = query(&path, &HistoryQuery::Previous).await.unwrap();
+        assert_eq!(previous, vec![expected[1]]);
+        let missing = HistoryQuery::Workspace("nope".into());
+        assert!(query(&path, &missing).await.unwrap().is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,341 @@
+//! Handles for acting on one window, workspace or output without formatting
+//! `[con_id=N]` commands by hand. Handles hold a snapshot of the state they
+//! were built from and borrow an [I3](../struct.I3.html) for each call:
+//!
+//! ```no_run
+//! # use std::io;
+//! use tokio_i3ipc::{handle::WindowRef, I3};
+//!
+//! # async fn f() -> io::Result<()> {
+//! let mut i3 = I3::connect().await?;
+//! let tree = i3.get_tree().await?;
+//! if let Some(mut win) = WindowRef::focused(&tree) {
+//!     win.move_to_workspace(&mut i3, "3").await?;
+//!     win.mark(&mut i3, "moved").await?;
+//!     win.refresh(&mut i3).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{
+    command::{self, quote},
+    reply,
+    util::check_success,
+    I3,
+};
+
+/// A window (or any other container) identified by its con_id
+#[derive(Debug, Clone)]
+pub struct WindowRef {
+    node: reply::Node,
+}
+
+impl WindowRef {
+    pub fn new(node: reply::Node) -> Self {
+        WindowRef { node }
+    }
+
+    /// The focused window in `tree`, if any
+    pub fn focused(tree: &reply::Node) -> Option<Self> {
+        tree.find(|n| n.focused && n.window.is_some())
+            .map(|n| WindowRef::new(n.clone()))
+    }
+
+    pub fn id(&self) -> usize {
+        self.node.id
+    }
+
+    /// State as of creation or the last `refresh`
+    pub fn node(&self) -> &reply::Node {
+        &self.node
+    }
+
+    /// Run `cmd` on this container only
+    pub async fn command<S, P>(&self, i3: &mut I3<S>, cmd: P) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+        P: AsRef<str>,
+    {
+        check_success(
+            i3.run_command(format!("[con_id={}] {}", self.node.id, cmd.as_ref()))
+                .await?,
+        )
+    }
+
+    pub async fn focus<S>(&self, i3: &mut I3<S>) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.command(i3, "focus").await
+    }
+
+    pub async fn kill<S>(&self, i3: &mut I3<S>) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.command(i3, "kill").await
+    }
+
+    pub async fn move_to_workspace<S, P>(&self, i3: &mut I3<S>, workspace: P) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+        P: AsRef<str>,
+    {
+        self.command(
+            i3,
+            format!("move container to workspace {}", quote(workspace.as_ref())),
+        )
+        .await
+    }
+
+    pub async fn move_to_output<S, P>(&self, i3: &mut I3<S>, output: P) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+        P: AsRef<str>,
+    {
+        self.command(
+            i3,
+            format!("move container to output {}", quote(output.as_ref())),
+        )
+        .await
+    }
+
+    pub async fn set_floating<S>(&self, i3: &mut I3<S>, floating: bool) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let state = if floating { "enable" } else { "disable" };
+        self.command(i3, format!("floating {state}")).await
+    }
+
+    /// Add `mark`, keeping any marks already set
+    pub async fn mark<S, P>(&self, i3: &mut I3<S>, mark: P) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+        P: AsRef<str>,
+    {
+        self.command(i3, format!("mark --add {}", quote(mark.as_ref())))
+            .await
+    }
+
+    pub async fn unmark<S, P>(&self, i3: &mut I3<S>, mark: P) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+        P: AsRef<str>,
+    {
+        self.command(i3, format!("unmark {}", quote(mark.as_ref())))
+            .await
+    }
+
+    /// Set the title shown in the window decoration, `%title` is replaced by
+    /// the window's own title
+    pub async fn rename<S, P>(&self, i3: &mut I3<S>, title_format: P) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+        P: AsRef<str>,
+    {
+        self.command(i3, format!("title_format {}", quote(title_format.as_ref())))
+            .await
+    }
+
+    /// Re-read this container from the tree, `ErrorKind::NotFound` if it has
+    /// closed
+    pub async fn refresh<S>(&mut self, i3: &mut I3<S>) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let tree = i3.get_tree().await?;
+        let id = self.node.id;
+        self.node = tree
+            .find(|n| n.id == id)
+            .cloned()
+            .ok_or_else(|| not_found(format!("container {id} no longer exists")))?;
+        Ok(())
+    }
+}
+
+impl From<reply::Node> for WindowRef {
+    fn from(node: reply::Node) -> Self {
+        WindowRef::new(node)
+    }
+}
+
+/// A workspace, identified by name
+#[derive(Debug, Clone)]
+pub struct WorkspaceRef {
+    workspace: reply::Workspace,
+}
+
+impl WorkspaceRef {
+    pub fn new(workspace: reply::Workspace) -> Self {
+        WorkspaceRef { workspace }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.workspace.name
+    }
+
+    /// State as of creation or the last `refresh`
+    pub fn workspace(&self) -> &reply::Workspace {
+        &self.workspace
+    }
+
+    pub async fn focus<S>(&self, i3: &mut I3<S>) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        check_success(
+            i3.run_command(command::focus_workspace(self.name()).to_string())
+                .await?,
+        )
+    }
+
+    /// Close every window on the workspace. This goes by criteria, which
+    /// can't match an empty workspace: nothing is closed, and i3 may report
+    /// that no window matched. i3 removes empty workspaces once they lose
+    /// focus anyway.
+    pub async fn kill<S>(&self, i3: &mut I3<S>) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        check_success(
+            i3.run_command(format!("[{}] kill", self.criteria()))
+                .await?,
+        )
+    }
+
+    pub async fn rename<S, P>(&mut self, i3: &mut I3<S>, name: P) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+        P: AsRef<str>,
+    {
+        check_success(
+            i3.run_command(format!(
+                "rename workspace {} to {}",
+                quote(self.name()),
+                quote(name.as_ref())
+            ))
+            .await?,
+        )?;
+        self.workspace.name = name.as_ref().to_owned();
+        Ok(())
+    }
+
+    /// Move the workspace to `output`, focusing it first. Criteria can't
+    /// select a workspace with no windows, so this goes by focus instead and
+    /// works for empty workspaces too.
+    pub async fn move_to_output<S, P>(&self, i3: &mut I3<S>, output: P) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+        P: AsRef<str>,
+    {
+        let cmds = [
+            command::focus_workspace(self.name()),
+            command::Command::new(format!(
+                "move workspace to output {}",
+                quote(output.as_ref())
+            )),
+        ];
+        check_success(i3.run_command(command::join(&cmds)?).await?)
+    }
+
+    /// Re-read this workspace by name, `ErrorKind::NotFound` if i3 has
+    /// removed it
+    pub async fn refresh<S>(&mut self, i3: &mut I3<S>) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.workspace = i3
+            .get_workspaces()
+            .await?
+            .into_iter()
+            .find(|w| w.name == self.workspace.name)
+            .ok_or_else(|| not_found(format!("workspace {} no longer exists", self.name())))?;
+        Ok(())
+    }
+
+    fn criteria(&self) -> String {
+        format!(
+            "workspace=\"^{}$\"",
+            crate::criteria::escape(&self.workspace.name)
+        )
+    }
+}
+
+impl From<reply::Workspace> for WorkspaceRef {
+    fn from(workspace: reply::Workspace) -> Self {
+        WorkspaceRef::new(workspace)
+    }
+}
+
+/// An output, identified by name
+#[derive(Debug, Clone)]
+pub struct OutputRef {
+    output: reply::Output,
+}
+
+impl OutputRef {
+    pub fn new(output: reply::Output) -> Self {
+        OutputRef { output }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.output.name
+    }
+
+    /// State as of creation or the last `refresh`
+    pub fn output(&self) -> &reply::Output {
+        &self.output
+    }
+
+    pub async fn focus<S>(&self, i3: &mut I3<S>) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        check_success(
+            i3.run_command(format!("focus output {}", quote(self.name())))
+                .await?,
+        )
+    }
+
+    /// The workspace currently visible on this output
+    pub async fn current_workspace<S>(&self, i3: &mut I3<S>) -> io::Result<Option<WorkspaceRef>>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        Ok(i3
+            .get_workspaces()
+            .await?
+            .into_iter()
+            .find(|w| w.visible && w.output == self.output.name)
+            .map(WorkspaceRef::new))
+    }
+
+    /// Re-read this output by name, `ErrorKind::NotFound` if it was unplugged
+    pub async fn refresh<S>(&mut self, i3: &mut I3<S>) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        self.output = i3
+            .get_outputs()
+            .await?
+            .into_iter()
+            .find(|o| o.name == self.output.name)
+            .ok_or_else(|| not_found(format!("output {} no longer exists", self.name())))?;
+        Ok(())
+    }
+}
+
+impl From<reply::Output> for OutputRef {
+    fn from(output: reply::Output) -> Self {
+        OutputRef::new(output)
+    }
+}
+
+fn not_found(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, msg)
+}
@@ -76,58 +76,244 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! ## Running services
+//!
+//! The `run_*` methods on [I3](struct.I3.html) react to events until their
+//! stream ends. They send commands on the connection they're called on, so
+//! the events have to come from a second connection, subscribed to the
+//! event types each method names:
+//! ```no_run
+//! # use std::io;
+//! use tokio_i3ipc::{autotile::Autotiler, event::Subscribe, I3};
+//!
+//! #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> io::Result<()> {
+//! let mut i3 = I3::connect().await?;
+//! let mut events = I3::connect().await?;
+//! events.subscribe([Subscribe::Window]).await?;
+//! i3.run_autotiler(&Autotiler::default(), &mut events.listen())
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
 
 pub use i3ipc_types::*;
+pub mod autotile;
 pub mod codec;
+pub mod focus_history;
+pub mod focus_service;
+pub mod handle;
+pub mod rebalance;
+pub mod rule_engine;
+pub mod rules;
+pub mod tiling;
 mod util;
+pub mod wait;
 
 pub use util::*;
+pub use wait::EventStreamExt;
 
 use serde::de::DeserializeOwned;
-use std::io;
+use std::{
+    io,
+    os::unix::{io::RawFd, net::UnixStream as StdUnixStream, prelude::FromRawFd},
+    path::Path,
+    time::Duration,
+};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net::UnixStream,
 };
+use tokio_stream::{Stream, StreamExt};
 use tokio_util::codec::FramedRead;
 
-/// Newtype wrapper for `UnixStream` that implements i3's IPC
+/// Newtype wrapper for a stream that implements i3's IPC. Defaults to
+/// `UnixStream`, but any `AsyncRead + AsyncWrite` transport can be used (see
+/// [from_stream](struct.I3.html#method.from_stream))
 #[derive(Debug)]
-pub struct I3 {
-    stream: UnixStream,
+pub struct I3<S = UnixStream> {
+    stream: S,
+    max_frame_len: usize,
 }
 
-// Implement `Future` for [I3](struct.I3.html) so it can be polled into a ready
-// `UnixStream`
+impl<S> I3Protocol for I3<S> {}
+
 impl I3 {
+    /// Connects to I3 over `UnixStream`
+    pub async fn connect() -> io::Result<Self> {
+        Ok(I3::from_stream(UnixStream::connect(socket_path()?).await?))
+    }
+
+    /// Connects to the i3 (or sway) socket at `path`, skipping socket discovery
+    pub async fn connect_to<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(I3::from_stream(UnixStream::connect(path.as_ref()).await?))
+    }
+
+    /// Wraps an already connected std `UnixStream`. The stream is put into
+    /// non-blocking mode. Must be called from within a tokio runtime.
+    pub fn from_std(stream: StdUnixStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        Ok(I3::from_stream(UnixStream::from_std(stream)?))
+    }
+
+    /// Wraps an inherited or socket-activated file descriptor. Must be called
+    /// from within a tokio runtime.
+    ///
+    /// # Safety
+    ///
+    /// `fd` must be an open unix stream socket connected to i3, and ownership
+    /// of it is transferred to the returned value.
+    pub unsafe fn from_raw_fd(fd: RawFd) -> io::Result<Self> {
+        Self::from_std(StdUnixStream::from_raw_fd(fd))
+    }
+
+    /// Run `cmd` and wait up to `timeout` for the first event matching
+    /// `predicate`. A second connection to the same socket is subscribed to
+    /// every event type *before* the command is sent, so nothing the command
+    /// causes can be missed. A command i3 reports as failed is returned as an
+    /// error, as is running out of time (`ErrorKind::TimedOut`).
+    ///
+    /// ```no_run
+    /// # use std::{io, time::Duration};
+    /// use tokio_i3ipc::{event::{Event, WindowChange}, I3};
+    ///
+    /// # async fn f() -> io::Result<()> {
+    /// let mut i3 = I3::connect().await?;
+    /// let evt = i3
+    ///     .run_and_wait(
+    ///         "workspace 3",
+    ///         |e| matches!(e, Event::Workspace(_)),
+    ///         Duration::from_secs(1),
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_and_wait<P, F>(
+        &mut self,
+        cmd: P,
+        mut predicate: F,
+        timeout: Duration,
+    ) -> io::Result<event::Event>
+    where
+        P: AsRef<str>,
+        F: FnMut(&event::Event) -> bool,
+    {
+        self.run_and_wait_map(cmd, |evt| predicate(&evt).then_some(evt), timeout)
+            .await
+    }
+
+    /// Like [run_and_wait](struct.I3.html#method.run_and_wait) but waits for a
+    /// new window matching `predicate`, e.g. `exec firefox` and the first
+    /// window with class `firefox`
+    pub async fn run_and_wait_window<P, F>(
+        &mut self,
+        cmd: P,
+        mut predicate: F,
+        timeout: Duration,
+    ) -> io::Result<reply::Node>
+    where
+        P: AsRef<str>,
+        F: FnMut(&reply::Node) -> bool,
+    {
+        self.run_and_wait_map(
+            cmd,
+            |evt| match evt {
+                event::Event::Window(w)
+                    if w.change == event::WindowChange::New && predicate(&w.container) =>
+                {
+                    Some(w.container)
+                }
+                _ => None,
+            },
+            timeout,
+        )
+        .await
+    }
+
+    async fn run_and_wait_map<P, T, F>(
+        &mut self,
+        cmd: P,
+        mut f: F,
+        timeout: Duration,
+    ) -> io::Result<T>
+    where
+        P: AsRef<str>,
+        F: FnMut(event::Event) -> Option<T>,
+    {
+        let addr = self.stream.peer_addr()?;
+        let path = addr.as_pathname().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "i3 connection has no socket path to subscribe on",
+            )
+        })?;
+        let mut sub = I3::connect_to(path).await?;
+        sub.subscribe(event::Subscribe::ALL).await?;
+        let mut events = sub.listen();
+
+        util::check_success(self.run_command(cmd).await?)?;
+
+        let wait = async {
+            while let Some(evt) = events.next().await {
+                if let Some(found) = f(evt?) {
+                    return Ok(found);
+                }
+            }
+            Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "event stream closed",
+            ))
+        };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "no matching event"))?
+    }
+}
+
+impl<S> I3<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Use any `AsyncRead + AsyncWrite` transport to talk to i3, e.g. one half
+    /// of `tokio::io::duplex` or a stream wrapped with logging
+    pub fn from_stream(stream: S) -> Self {
+        I3 {
+            stream,
+            max_frame_len: frame::DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    /// Reject replies and events with payloads over `len` bytes, defaults to
+    /// [DEFAULT_MAX_FRAME_LEN](frame/constant.DEFAULT_MAX_FRAME_LEN.html)
+    pub fn set_max_frame_len(&mut self, len: usize) {
+        self.max_frame_len = len;
+    }
+
+    /// Consumes `I3`, returning the underlying stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
     /// Sends a message and payload, used for `get_*` commands and `run_command`
     async fn _send_msg<P>(&mut self, msg: msg::Msg, payload: Option<P>) -> io::Result<()>
     where
         P: AsRef<str>,
     {
-        let buf = self.stream._encode_msg(msg, payload);
+        let buf = self._encode_msg(msg, payload);
         self.stream.write_all(&buf).await
     }
 
     async fn _decode_msg(&mut self) -> io::Result<(u32, Vec<u8>)> {
-        let mut init = [0_u8; 14];
-        let _len = self.stream.read_exact(&mut init).await?;
-
-        assert!(&init[0..6] == MAGIC.as_bytes(), "Magic str not received");
-        let payload_len = u32::from_ne_bytes([init[6], init[7], init[8], init[9]]) as usize;
-        let msg_type = u32::from_ne_bytes([init[10], init[11], init[12], init[13]]);
+        let mut init = [0_u8; frame::HEADER_LEN];
+        self.stream.read_exact(&mut init).await?;
+        let header = frame::Header::parse_with_limit(&init, self.max_frame_len)?;
 
-        let mut payload = vec![0_u8; payload_len];
-        let _len_read = self.stream.read_exact(&mut payload).await?;
+        let mut payload = vec![0_u8; header.len as usize];
+        self.stream.read_exact(&mut payload).await?;
 
-        Ok((msg_type, payload))
-    }
-
-    /// Connects to I3 over `UnixStream`
-    pub async fn connect() -> io::Result<Self> {
-        Ok(I3 {
-            stream: UnixStream::connect(socket_path()?).await?,
-        })
+        Ok((header.msg_type, payload))
     }
 
     pub async fn send_msg_body<P>(&mut self, msg: msg::Msg, payload: P) -> io::Result<()>
@@ -147,10 +333,7 @@ impl I3 {
         D: DeserializeOwned,
     {
         let (msg_type, payload) = self._decode_msg().await?;
-        Ok(MsgResponse {
-            msg_type: msg_type.into(),
-            body: serde_json::from_slice(&payload[..])?,
-        })
+        MsgResponse::new(msg_type, payload)
     }
 
     /// Like `read_msg` but for `event::Event`
@@ -170,117 +353,502 @@ impl I3 {
         self.read_msg().await
     }
 
+    /// Send any [Request](request/trait.Request.html) and read its reply. The
+    /// reply type is determined by the request, a reply to a different
+    /// message type is returned as an error.
+    pub async fn request<R: request::Request>(&mut self, req: R) -> io::Result<R::Reply> {
+        let buf = req.encode()?;
+        self.stream.write_all(&buf).await?;
+        let (msg_type, payload) = self._decode_msg().await?;
+        R::decode_reply(msg_type, &payload)
+    }
+
     /// Returns a Future that will send a [Subscribe](event/enum.Subscribe.html)
     /// message to i3 along with a list of events to listen to.
     pub async fn subscribe<E>(&mut self, events: E) -> io::Result<reply::Success>
     where
         E: AsRef<[event::Subscribe]>,
     {
-        let sub_json = serde_json::to_string(events.as_ref())?;
-        self.send_msg_body(msg::Msg::Subscribe, sub_json).await?;
-        Ok(self.read_msg::<reply::Success>().await?.body)
+        self.request(request::Subscribe(events.as_ref().to_vec()))
+            .await
     }
 
     /// Provides a type that implements `Stream` so you can `await` events in a
     /// loop
-    pub fn listen(self) -> FramedRead<UnixStream, codec::EventCodec> {
-        FramedRead::new(self.stream, codec::EventCodec)
+    pub fn listen(self) -> FramedRead<S, codec::EventCodec> {
+        FramedRead::new(
+            self.stream,
+            codec::EventCodec::with_max_frame_len(self.max_frame_len),
+        )
     }
 
     /// Run an arbitrary command on i3. Response is a `Vec` of success
     /// true/false.
-    pub async fn run_command<S: AsRef<str>>(
+    pub async fn run_command<P: AsRef<str>>(
         &mut self,
-        payload: S,
+        payload: P,
     ) -> io::Result<Vec<reply::Success>> {
-        self.send_msg_body(msg::Msg::RunCommand, payload).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::RunCommand(payload.as_ref().to_owned()))
+            .await
+    }
+
+    /// Run several commands, pairing each with i3's reply to it. Normally the
+    /// commands are joined and sent as one message, which i3 runs to the end
+    /// whatever fails. With `stop_on_failure` they are sent one at a time
+    /// instead, and nothing after the first failure is run.
+    pub async fn run_batch(
+        &mut self,
+        commands: Vec<command::Command>,
+        stop_on_failure: bool,
+    ) -> io::Result<Vec<command::CommandResult>> {
+        if !stop_on_failure {
+            let results = self.run_command(command::join(&commands)?).await?;
+            return command::zip(commands, results);
+        }
+        let mut out = Vec::with_capacity(commands.len());
+        for cmd in commands {
+            let payload = command::join(std::slice::from_ref(&cmd))?;
+            let results = self.run_command(payload).await?;
+            out.extend(command::zip(vec![cmd], results)?);
+            if out.last().is_some_and(|r| !r.is_success()) {
+                break;
+            }
+        }
+        Ok(out)
+    }
+
+    /// Save the workspace called `name` in the format `append_layout` reads,
+    /// see the [layout](layout/index.html) module. `ErrorKind::NotFound` if
+    /// there is no such workspace.
+    pub async fn save_layout(
+        &mut self,
+        name: &str,
+        props: &layout::SwallowProps,
+    ) -> io::Result<layout::Layout> {
+        let tree = self.get_tree().await?;
+        let ws = tree.find_workspace(name).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no workspace {name}"))
+        })?;
+        Ok(layout::Layout::from_workspace(ws, props))
+    }
+
+    /// Switch to `workspace`, append the layout file at `path` and run each of
+    /// `execs`, which should start the programs whose windows fill the
+    /// layout's placeholders
+    pub async fn restore_layout<P: AsRef<Path>>(
+        &mut self,
+        workspace: &str,
+        path: P,
+        execs: &[String],
+    ) -> io::Result<()> {
+        let path = path.as_ref().to_str().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "layout path is not utf-8")
+        })?;
+        let mut cmds = vec![
+            command::focus_workspace(workspace),
+            command::Command::new(format!("append_layout {}", command::quote(path))),
+        ];
+        cmds.extend(
+            execs.iter().map(|e| {
+                command::Command::new(format!("exec --no-startup-id {}", command::quote(e)))
+            }),
+        );
+        let results = self.run_batch(cmds, true).await?;
+        util::check_results(&results)
+    }
+
+    /// Snapshot every workspace, see the [session](session/index.html)
+    /// module. `resolve` returns the command relaunching a window.
+    pub async fn save_session<F>(
+        &mut self,
+        props: &layout::SwallowProps,
+        resolve: F,
+    ) -> io::Result<session::Session>
+    where
+        F: Fn(&reply::Node) -> Option<String>,
+    {
+        let workspaces = self.get_workspaces().await?;
+        let tree = self.get_tree().await?;
+        Ok(session::Session::capture(
+            &tree,
+            &workspaces,
+            props,
+            resolve,
+        ))
+    }
+
+    /// Recreate every workspace in `session` on its output, append its layout
+    /// and relaunch its windows, then focus what was focused. Workspaces whose
+    /// output isn't connected stay on the focused one. Layouts are written to
+    /// files in `layout_dir` for i3 to read.
+    pub async fn restore_session<P: AsRef<Path>>(
+        &mut self,
+        session: &session::Session,
+        layout_dir: P,
+    ) -> io::Result<()> {
+        let outputs = self.get_outputs().await?;
+        for (i, ws) in session.workspaces.iter().enumerate() {
+            let path = layout_dir.as_ref().join(format!("workspace-{i}.json"));
+            std::fs::write(&path, ws.layout().to_json()?)?;
+            let path = path.to_str().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "layout path is not utf-8")
+            })?;
+            let results = self
+                .run_batch(ws.restore_commands(path, &outputs), true)
+                .await?;
+            util::check_results(&results)?;
+        }
+        let results = self.run_batch(session.focus_commands(), true).await?;
+        util::check_results(&results)
+    }
+
+    /// How outputs are arranged and which workspaces they hold, from
+    /// `get_outputs` and `get_workspaces`
+    pub async fn get_output_layout(&mut self) -> io::Result<output_layout::OutputLayout> {
+        let outputs = self.get_outputs().await?;
+        let workspaces = self.get_workspaces().await?;
+        Ok(output_layout::OutputLayout::new(outputs, workspaces))
+    }
+
+    /// Move workspaces that aren't on their target output back to it, then
+    /// record where everything ended up. See the
+    /// [rebalance](rebalance/index.html) module.
+    pub async fn rebalance(
+        &mut self,
+        rebalancer: &mut rebalance::Rebalancer,
+    ) -> io::Result<Vec<command::CommandResult>> {
+        Ok(self.rebalance_layout(rebalancer).await?.0)
+    }
+
+    /// [rebalance](#method.rebalance), also returning the layout it observed
+    async fn rebalance_layout(
+        &mut self,
+        rebalancer: &mut rebalance::Rebalancer,
+    ) -> io::Result<(Vec<command::CommandResult>, output_layout::OutputLayout)> {
+        let cmds = rebalancer.plan(&self.get_output_layout().await?);
+        let results = if cmds.is_empty() {
+            Vec::new()
+        } else {
+            self.run_batch(cmds, false).await?
+        };
+        let layout = self.get_output_layout().await?;
+        rebalancer.observe(&layout);
+        Ok((results, layout))
+    }
+
+    /// Keep workspaces on their outputs as outputs come and go, until
+    /// `events` ends. `events` needs `Subscribe::Output` and
+    /// `Subscribe::Workspace`, see
+    /// [running services](index.html#running-services). Output events
+    /// trigger a [rebalance](#method.rebalance), and workspaces moved or
+    /// created in between are recorded as preferred where they land.
+    /// Workspace events arriving after the outputs changed but before the
+    /// output event, e.g. the `init` i3 sends for a workspace it creates on
+    /// a new output, record nothing, so the rebalance still sees where the
+    /// displaced workspaces belong.
+    pub async fn run_rebalancer<E>(
+        &mut self,
+        rebalancer: &mut rebalance::Rebalancer,
+        events: &mut E,
+    ) -> io::Result<()>
+    where
+        E: Stream<Item = io::Result<event::Event>> + Unpin,
+    {
+        let (_, mut settled) = self.rebalance_layout(rebalancer).await?;
+        while let Some(evt) = events.next().await {
+            match evt? {
+                event::Event::Output(_) => {
+                    settled = self.rebalance_layout(rebalancer).await?.1;
+                }
+                event::Event::Workspace(ws)
+                    if matches!(
+                        ws.change,
+                        event::WorkspaceChange::Init
+                            | event::WorkspaceChange::Move
+                            | event::WorkspaceChange::Rename
+                    ) =>
+                {
+                    let layout = self.get_output_layout().await?;
+                    let names = |l: &output_layout::OutputLayout| {
+                        l.outputs()
+                            .iter()
+                            .map(|o| o.name.clone())
+                            .collect::<Vec<_>>()
+                    };
+                    if names(&layout) == names(&settled) {
+                        rebalancer.observe(&layout);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Split container `id` the way `tiler` decides, returning the command
+    /// that was run if any. See the [autotile](autotile/index.html) module.
+    pub async fn autotile(
+        &mut self,
+        tiler: &autotile::Autotiler,
+        id: usize,
+    ) -> io::Result<Option<command::CommandResult>> {
+        let Some(cmd) = tiler.split_for(&self.get_tree().await?, id) else {
+            return Ok(None);
+        };
+        Ok(self.run_batch(vec![cmd], false).await?.pop())
+    }
+
+    /// Autotile every window that is created or focused until `events` ends.
+    /// `events` needs `Subscribe::Window`, see
+    /// [running services](index.html#running-services). A split failing,
+    /// e.g. because the window closed in the meantime, isn't an error.
+    pub async fn run_autotiler<E>(
+        &mut self,
+        tiler: &autotile::Autotiler,
+        events: &mut E,
+    ) -> io::Result<()>
+    where
+        E: Stream<Item = io::Result<event::Event>> + Unpin,
+    {
+        while let Some(evt) = events.next().await {
+            if let event::Event::Window(win) = evt? {
+                if matches!(
+                    win.change,
+                    event::WindowChange::New | event::WindowChange::Focus
+                ) {
+                    self.autotile(tiler, win.container.id).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep `history` up to date until `events` ends, starting over from the
+    /// current tree. `events` needs `Subscribe::Window` and
+    /// `Subscribe::Workspace`, see
+    /// [running services](index.html#running-services). The
+    /// [focus_service](focus_service/index.html) module answers queries
+    /// while this runs.
+    pub async fn run_focus_history<E>(
+        &mut self,
+        history: &std::sync::Mutex<focus_history::FocusHistory>,
+        events: &mut E,
+    ) -> io::Result<()>
+    where
+        E: Stream<Item = io::Result<event::Event>> + Unpin,
+    {
+        let tree = self.get_tree().await?;
+        *focus_service::lock(history) = focus_history::FocusHistory::from_tree(&tree);
+        while let Some(evt) = events.next().await {
+            focus_service::lock(history).handle(&evt?);
+        }
+        Ok(())
+    }
+
+    /// Bring every workspace `manager` has a layout for into shape, see the
+    /// [tiling](tiling/index.html) module
+    pub async fn apply_layouts(
+        &mut self,
+        manager: &mut tiling::LayoutManager,
+    ) -> io::Result<Vec<command::CommandResult>> {
+        let cmds = manager.plan(&self.get_tree().await?);
+        if cmds.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.run_batch(cmds, false).await
+    }
+
+    /// [apply_layouts](#method.apply_layouts) whenever windows come, go or
+    /// move, until `events` ends. `events` needs `Subscribe::Window`, see
+    /// [running services](index.html#running-services).
+    pub async fn run_layout_manager<E>(
+        &mut self,
+        manager: &mut tiling::LayoutManager,
+        events: &mut E,
+    ) -> io::Result<()>
+    where
+        E: Stream<Item = io::Result<event::Event>> + Unpin,
+    {
+        self.apply_layouts(manager).await?;
+        while let Some(evt) = events.next().await {
+            if let event::Event::Window(win) = evt? {
+                if matches!(
+                    win.change,
+                    event::WindowChange::New
+                        | event::WindowChange::Close
+                        | event::WindowChange::Move
+                        | event::WindowChange::Floating
+                ) {
+                    self.apply_layouts(manager).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply `engine`'s rules to window events until `events` ends.
+    /// `events` needs `Subscribe::Window`, see
+    /// [running services](index.html#running-services). The rules file is
+    /// checked for changes before each event; if it no longer parses or
+    /// names an unregistered callback a warning is logged and the old rules
+    /// stay. Commands that fail, e.g. because the window closed in the
+    /// meantime, are logged too, as are unknown callbacks and callbacks
+    /// returning commands that can't be batched. In a dry run matches are
+    /// only logged.
+    pub async fn run_rules<E>(
+        &mut self,
+        engine: &mut rule_engine::RuleEngine,
+        events: &mut E,
+    ) -> io::Result<()>
+    where
+        E: Stream<Item = io::Result<event::Event>> + Unpin,
+    {
+        while let Some(evt) = events.next().await {
+            let event::Event::Window(win) = evt? else {
+                continue;
+            };
+            if let Err(e) = engine.reload_if_changed() {
+                log::warn!("keeping previous window rules: {e}");
+            }
+            let planned = match engine.plan(&win) {
+                Ok(planned) => planned,
+                Err(e) => {
+                    log::warn!("skipping window rules for {}: {e}", win.container.id);
+                    continue;
+                }
+            };
+            let mut cmds = Vec::new();
+            for planned in planned {
+                if engine.is_dry_run() {
+                    log::info!("{planned}");
+                } else {
+                    cmds.extend(planned.commands);
+                }
+            }
+            if cmds.is_empty() {
+                continue;
+            }
+            let results = match self.run_batch(cmds, false).await {
+                Ok(results) => results,
+                // a callback returned a command that can't be batched
+                Err(e) if e.kind() == io::ErrorKind::InvalidInput => {
+                    log::warn!("skipping window rules for {}: {e}", win.container.id);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            for result in results {
+                if !result.is_success() {
+                    log::warn!(
+                        "{} failed: {}",
+                        result.command,
+                        result.error().unwrap_or_default()
+                    );
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Future for getting the current
     /// [Workspaces](../reply/struct.Workspace.html), sends
     /// [Workspaces](../msg/enum.Msg.html#variant.Workspaces)
     pub async fn get_workspaces(&mut self) -> io::Result<reply::Workspaces> {
-        self.send_msg(msg::Msg::Workspaces).await?;
-        let resp: MsgResponse<Vec<reply::Workspace>> = self.read_msg().await?;
-        Ok(resp.body)
+        self.request(request::Workspaces).await
     }
 
     /// Future that gets all [Outputs](../reply/struct.Outputs.html), sends
     /// [Outputs](../msg/enum.Msg.html#variant.Outputs)
     pub async fn get_outputs(&mut self) -> io::Result<reply::Outputs> {
-        self.send_msg(msg::Msg::Outputs).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Outputs).await
     }
 
     /// Future to get complete [Node](../reply/struct.Node.html), sends
     /// [Tree](../msg/enum.Msg.html#variant.Tree)
     pub async fn get_tree(&mut self) -> io::Result<reply::Node> {
-        self.send_msg(msg::Msg::Tree).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Tree).await
     }
 
     /// Get all [Marks](../reply/struct.Marks.html), sends
     /// [Marks](../msg/enum.Msg.html#variant.Marks)
     pub async fn get_marks(&mut self) -> io::Result<reply::Marks> {
-        self.send_msg(msg::Msg::Marks).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Marks).await
     }
 
     /// Future to get all [BarIds](../reply/struct.BarIds.html), sends
     /// [BarConfig](../msg/enum.Msg.html#variant.BarConfig)
     pub async fn get_bar_ids(&mut self) -> io::Result<reply::BarIds> {
-        self.send_msg(msg::Msg::BarConfig).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::BarIds).await
     }
 
     /// Future to get configs associated with a bar id responds with
     /// [BarConfig](../reply/struct.BarConfig.html), sends
     /// [BarConfig](../msg/enum.Msg.html#variant.BarConfig)
-    pub async fn get_bar_config<S: AsRef<str>>(
+    pub async fn get_bar_config<P: AsRef<str>>(
         &mut self,
-        bar_id: S,
+        bar_id: P,
     ) -> io::Result<reply::BarConfig> {
-        self.send_msg_body(msg::Msg::BarConfig, bar_id).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::BarConfig(bar_id.as_ref().to_owned()))
+            .await
     }
 
     /// Get i3 version
     pub async fn get_version(&mut self) -> io::Result<reply::Version> {
-        self.send_msg(msg::Msg::Version).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Version).await
     }
 
     /// Future to get [BindingModes](../reply/struct.BindingModes.html), sends
     /// [BindingModes](../msg/enum.Msg.html#variant.BindingModes)
     pub async fn get_binding_modes(&mut self) -> io::Result<reply::BindingModes> {
-        self.send_msg(msg::Msg::BindingModes).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::BindingModes).await
     }
 
     /// Future for [Config](../reply/struct.Config.html), sends
     /// [Config](../msg/enum.Msg.html#variant.Config)
     pub async fn get_config(&mut self) -> io::Result<reply::Config> {
-        self.send_msg(msg::Msg::Config).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Config).await
     }
 
     /// Future sends [Tick](../msg/enum.Msg.html#variant.Tick)
     pub async fn get_tick(&mut self) -> io::Result<reply::Success> {
-        self.send_msg(msg::Msg::Tick).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Tick::default()).await
+    }
+
+    /// Future sends [Tick](../msg/enum.Msg.html#variant.Tick) with `payload`,
+    /// which subscribers receive as a [TickData](../event/struct.TickData.html)
+    pub async fn send_tick<P: AsRef<str>>(&mut self, payload: P) -> io::Result<reply::Success> {
+        self.request(request::Tick(payload.as_ref().to_owned()))
+            .await
+    }
+
+    /// Send a uniquely named tick and wait for it to come back on `events`, a
+    /// stream from another connection subscribed to
+    /// [Tick](../event/enum.Subscribe.html#variant.Tick). i3 delivers events
+    /// in order, so once this resolves every event caused by commands sent
+    /// before it has been received. Those events are returned rather than
+    /// dropped. A tick i3 rejects is an `InvalidData` error.
+    pub async fn barrier<E>(&mut self, events: &mut E) -> io::Result<Vec<event::Event>>
+    where
+        E: Stream<Item = io::Result<event::Event>> + Unpin,
+    {
+        let mut barrier = request::Barrier::new();
+        barrier.check(&self.request(barrier.tick()).await?)?;
+        while let Some(evt) = events.next().await {
+            if barrier.push(evt?) {
+                return Ok(barrier.into_events());
+            }
+        }
+        Err(request::Barrier::closed())
     }
 
     /// Future [Sync](../msg/enum.Msg.html#variant.Sync)
     pub async fn get_sync(&mut self) -> io::Result<reply::Success> {
-        self.send_msg(msg::Msg::Sync).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::Sync).await
     }
 
     /// Future to get [BindingState](../reply/struct.BindingState.html), sends
-    /// [BindingState](../msg/enum.Msg.html#variant.BindingModes)
+    /// [BindingState](../msg/enum.Msg.html#variant.BindingState)
     pub async fn get_binding_state(&mut self) -> io::Result<reply::BindingState> {
-        self.send_msg(msg::Msg::BindingModes).await?;
-        Ok(self.read_msg().await?.body)
+        self.request(request::BindingState).await
     }
 }
@@ -0,0 +1,204 @@
+//! Putting workspaces back where they belong after outputs come and go. When
+//! an output disappears i3 moves its workspaces to one that remains, and
+//! they stay there when it comes back. A [Rebalancer](struct.Rebalancer.html)
+//! remembers the output each workspace was last on and plans the commands
+//! moving it back, with [Profile](struct.Profile.html)s overriding that for
+//! particular sets of connected outputs, e.g. docked or laptop only.
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    command::{focus_workspace, quote, Command},
+    output_layout::OutputLayout,
+};
+
+/// Where workspaces go while exactly `outputs` are connected
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct Profile {
+    pub outputs: BTreeSet<String>,
+    /// Workspace name to output name
+    #[serde(default)]
+    pub workspaces: BTreeMap<String, String>,
+}
+
+impl Profile {
+    pub fn matches(&self, layout: &OutputLayout) -> bool {
+        self.outputs.len() == layout.outputs().len()
+            && layout
+                .outputs()
+                .iter()
+                .all(|o| self.outputs.contains(&o.name))
+    }
+}
+
+#[derive(Deserialize, Serialize, Eq, PartialEq, Debug, Clone, Default)]
+pub struct Rebalancer {
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+    /// Workspace name to the output it was last seen on while that output
+    /// was connected
+    #[serde(default)]
+    pub preferred: BTreeMap<String, String>,
+}
+
+impl Rebalancer {
+    pub fn new(profiles: Vec<Profile>) -> Self {
+        Rebalancer {
+            profiles,
+            preferred: BTreeMap::new(),
+        }
+    }
+
+    /// The first profile for the outputs connected in `layout`
+    pub fn profile(&self, layout: &OutputLayout) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.matches(layout))
+    }
+
+    /// Where `workspace` should be: the matching profile's choice, otherwise
+    /// its preferred output. `None` if neither is connected.
+    pub fn target<'a>(&'a self, workspace: &str, layout: &OutputLayout) -> Option<&'a str> {
+        self.profile(layout)
+            .and_then(|p| p.workspaces.get(workspace))
+            .into_iter()
+            .chain(self.preferred.get(workspace))
+            .find(|o| layout.get(o).is_some())
+            .map(String::as_str)
+    }
+
+    /// Record where workspaces are now. A workspace whose preferred output
+    /// is disconnected keeps its preference, it was only moved because its
+    /// output went away. Call this whenever workspaces move or appear so
+    /// moves made by hand are remembered.
+    pub fn observe(&mut self, layout: &OutputLayout) {
+        for ws in layout.workspaces() {
+            let displaced = self
+                .preferred
+                .get(&ws.name)
+                .is_some_and(|o| layout.get(o).is_none());
+            if !displaced {
+                self.preferred.insert(ws.name.clone(), ws.output.clone());
+            }
+        }
+    }
+
+    /// Commands moving every workspace that isn't on its target output, then
+    /// showing the workspaces that were visible and focusing the one that
+    /// was focused. Empty if nothing needs moving.
+    pub fn plan(&self, layout: &OutputLayout) -> Vec<Command> {
+        let mut cmds = Vec::new();
+        for ws in layout.workspaces() {
+            match self.target(&ws.name, layout) {
+                Some(output) if output != ws.output => {
+                    cmds.push(focus_workspace(&ws.name));
+                    cmds.push(Command::new(format!(
+                        "move workspace to output {}",
+                        quote(output)
+                    )));
+                }
+                _ => {}
+            }
+        }
+        if cmds.is_empty() {
+            return cmds;
+        }
+        let visible = layout
+            .workspaces()
+            .iter()
+            .filter(|ws| ws.visible && !ws.focused);
+        let focused = layout.workspaces().iter().filter(|ws| ws.focused);
+        cmds.extend(visible.chain(focused).map(|ws| focus_workspace(&ws.name)));
+        cmds
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reply::{Output, Rect, Workspace};
+
+    fn layout(outputs: &[&str], workspaces: &[(&str, &str, bool)]) -> OutputLayout {
+        let outputs = outputs
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                serde_json::from_value::<Output>(serde_json::json!({
+                    "name": name,
+                    "active": true,
+                    "primary": i == 0,
+                    "rect": Rect::new(i as isize * 1920, 0, 1920, 1080),
+                    "current_workspace": null,
+                }))
+                .unwrap()
+            })
+            .collect();
+        let workspaces = workspaces
+            .iter()
+            .map(|(name, output, focused)| {
+                serde_json::from_value::<Workspace>(serde_json::json!({
+                    "num": -1,
+                    "name": name,
+                    "visible": focused,
+                    "focused": focused,
+                    "urgent": false,
+                    "rect": Rect::default(),
+                    "output": output,
+                    "focus": [],
+                }))
+                .unwrap()
+            })
+            .collect();
+        OutputLayout::new(outputs, workspaces)
+    }
+
+    fn actions(cmds: Vec<Command>) -> Vec<String> {
+        cmds.into_iter().map(|c| c.action).collect()
+    }
+
+    #[test]
+    fn test_output_returns() {
+        let mut r = Rebalancer::default();
+        r.observe(&layout(
+            &["eDP-1", "DP-1"],
+            &[("1", "eDP-1", true), ("2", "DP-1", false)],
+        ));
+        // DP-1 unplugged, i3 moved "2" over
+        let undocked = layout(&["eDP-1"], &[("1", "eDP-1", true), ("2", "eDP-1", false)]);
+        r.observe(&undocked);
+        assert_eq!(r.preferred["2"], "DP-1");
+        assert!(r.plan(&undocked).is_empty());
+
+        let docked = layout(
+            &["eDP-1", "DP-1"],
+            &[
+                ("1", "eDP-1", true),
+                ("2", "eDP-1", false),
+                ("3", "DP-1", false),
+            ],
+        );
+        assert_eq!(
+            actions(r.plan(&docked)),
+            vec![
+                r#"workspace --no-auto-back-and-forth "2""#,
+                r#"move workspace to output "DP-1""#,
+                r#"workspace --no-auto-back-and-forth "1""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_profile() {
+        let docked: Profile = serde_json::from_value(serde_json::json!({
+            "outputs": ["eDP-1", "DP-1"],
+            "workspaces": {"1": "DP-1"},
+        }))
+        .unwrap();
+        let mut r = Rebalancer::new(vec![docked]);
+        let l = layout(&["eDP-1", "DP-1"], &[("1", "eDP-1", true)]);
+        r.observe(&l);
+        assert_eq!(r.target("1", &l), Some("DP-1"));
+        let laptop = layout(&["eDP-1"], &[("1", "eDP-1", true)]);
+        assert!(r.profile(&laptop).is_none());
+        assert_eq!(r.target("1", &laptop), Some("eDP-1"));
+    }
+}
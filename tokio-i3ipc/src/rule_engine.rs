@@ -0,0 +1,205 @@
+//! Running a [RuleSet](../rules/struct.RuleSet.html) against window events,
+//! see [run_rules](../struct.I3.html#method.run_rules). Callbacks registered
+//! by name can look at the event and anything else they like, and return
+//! more commands to run.
+use std::{
+    collections::HashMap,
+    fmt, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    command::Command,
+    event::WindowData,
+    rules::{Rule, RuleSet},
+};
+
+pub type Callback = Box<dyn Fn(&WindowData) -> Vec<Command> + Send + Sync>;
+
+#[derive(Default)]
+pub struct RuleEngine {
+    rules: RuleSet,
+    /// The rules file and its modification time when last read
+    source: Option<(PathBuf, Option<SystemTime>)>,
+    callbacks: HashMap<String, Callback>,
+    dry_run: bool,
+}
+
+impl RuleEngine {
+    pub fn new(rules: RuleSet) -> Self {
+        RuleEngine {
+            rules,
+            ..RuleEngine::default()
+        }
+    }
+
+    /// Read rules from `path`, see [RuleSet::load](../rules/struct.RuleSet.html#method.load).
+    /// The file is read again whenever it changes.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        let modified = modified(&path);
+        Ok(RuleEngine {
+            rules: RuleSet::load(&path)?,
+            source: Some((path, modified)),
+            ..RuleEngine::default()
+        })
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    /// Call `f` for `callback` actions naming `name`. The commands it
+    /// returns are run along with the rule's own.
+    pub fn callback<F>(&mut self, name: &str, f: F) -> &mut Self
+    where
+        F: Fn(&WindowData) -> Vec<Command> + Send + Sync + 'static,
+    {
+        self.callbacks.insert(name.to_owned(), Box::new(f));
+        self
+    }
+
+    /// In a dry run matches are logged and neither commands nor callbacks
+    /// are run
+    pub fn set_dry_run(&mut self, dry_run: bool) -> &mut Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Read the rules file again if it changed since it was last read,
+    /// returning whether it did. If it no longer parses, or names a callback
+    /// that isn't registered, the old rules are kept and the error returned.
+    pub fn reload_if_changed(&mut self) -> io::Result<bool> {
+        let Some((path, last)) = &mut self.source else {
+            return Ok(false);
+        };
+        let now = modified(path);
+        if now == *last {
+            return Ok(false);
+        }
+        *last = now;
+        let rules = RuleSet::load(path)?;
+        self.check_callbacks(&rules)?;
+        self.rules = rules;
+        Ok(true)
+    }
+
+    /// `InvalidInput` if `rules` name a callback that isn't registered
+    fn check_callbacks(&self, rules: &RuleSet) -> io::Result<()> {
+        for rule in &rules.rules {
+            for name in rule.callbacks() {
+                self.lookup(name)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> io::Result<&Callback> {
+        self.callbacks.get(name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no callback registered as {name:?}"),
+            )
+        })
+    }
+
+    /// Each rule matching `data` with the commands it results in. Callbacks
+    /// are only called outside of a dry run; an unknown one is an
+    /// `InvalidInput` error.
+    pub fn plan<'a>(&'a self, data: &'a WindowData) -> io::Result<Vec<Planned<'a>>> {
+        let id = data.container.id;
+        let mut out = Vec::new();
+        for rule in self.rules.matching(data) {
+            let mut commands = rule.commands(id);
+            for name in rule.callbacks() {
+                let f = self.lookup(name)?;
+                if !self.dry_run {
+                    commands.extend(f(data));
+                }
+            }
+            out.push(Planned {
+                rule,
+                container: id,
+                commands,
+            });
+        }
+        Ok(out)
+    }
+}
+
+/// What a rule does to a window
+#[derive(Debug, Clone)]
+pub struct Planned<'a> {
+    pub rule: &'a Rule,
+    pub container: usize,
+    pub commands: Vec<Command>,
+}
+
+impl fmt::Display for Planned<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} matched container {}",
+            self.rule.label(),
+            self.container
+        )?;
+        for cmd in &self.commands {
+            write!(f, "; {cmd}")?;
+        }
+        Ok(())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_reload_if_changed() {
+        let path = std::env::temp_dir().join(format!("rules-{}.json", std::process::id()));
+        let rules = |class: &str| {
+            format!(r#"{{"rules": [{{"criteria": {{"class": "{class}"}}, "actions": []}}]}}"#)
+        };
+        std::fs::write(&path, rules("A")).unwrap();
+        let mut engine = RuleEngine::load(&path).unwrap();
+        assert!(!engine.reload_if_changed().unwrap());
+
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        std::fs::write(&path, rules("B")).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(1))
+            .unwrap();
+        assert!(engine.reload_if_changed().unwrap());
+        assert_eq!(engine.rules().rules[0].criteria.class.as_deref(), Some("B"));
+
+        std::fs::write(&path, "not json").unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(2))
+            .unwrap();
+        assert!(engine.reload_if_changed().is_err());
+        assert_eq!(engine.rules().rules[0].criteria.class.as_deref(), Some("B"));
+
+        // commands i3 would split, and callbacks nobody registered, are rejected
+        // the same way
+        let bad = [
+            r#"{"rules": [{"criteria": {}, "actions": [{"command": "focus left, kill"}]}]}"#,
+            r#"{"rules": [{"criteria": {}, "actions": [{"callback": "missing"}]}]}"#,
+        ];
+        for (i, bad) in bad.iter().enumerate() {
+            std::fs::write(&path, bad).unwrap();
+            file.set_modified(SystemTime::now() + Duration::from_secs(3 + i as u64))
+                .unwrap();
+            assert!(engine.reload_if_changed().is_err());
+            assert_eq!(engine.rules().rules[0].criteria.class.as_deref(), Some("B"));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
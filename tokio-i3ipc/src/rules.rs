@@ -0,0 +1,253 @@
+//! Window rules applied from the client side, like `for_window` but able to
+//! react to any window event and to hand off to code. A rule matches the
+//! container of a [WindowData](../event/struct.WindowData.html) event against
+//! [Criteria](../criteria/struct.Criteria.html) and lists the actions to take.
+//!
+//! Rules are read from JSON, or from TOML with the `toml` feature:
+//!
+//! ```toml
+//! [[rules]]
+//! name = "float dialogs"
+//! criteria = { window_type = "dialog" }
+//! actions = [{ float = { geometry = { x = 100, y = 100, width = 800, height = 600 } } }]
+//!
+//! [[rules]]
+//! criteria = { class = "Firefox" }
+//! on = ["new", "title"]
+//! actions = [{ move_to_workspace = "2: web" }, { mark = "browser" }, { callback = "log" }]
+//! ```
+use serde::{Deserialize, Serialize};
+
+use std::{fs, io, path::Path};
+
+use crate::{
+    command::{self, quote, Command},
+    criteria::Criteria,
+    event::{WindowChange, WindowData},
+    reply::Rect,
+};
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+pub struct RuleSet {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct Rule {
+    /// Shown when the rule matches in a dry run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub criteria: Criteria,
+    /// Window events the rule applies to, only `new` if left out
+    #[serde(default = "new_only")]
+    pub on: Vec<WindowChange>,
+    pub actions: Vec<Action>,
+}
+
+fn new_only() -> Vec<WindowChange> {
+    vec![WindowChange::New]
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Any i3 command, run on the window
+    Command(String),
+    /// Make the window floating, optionally placing it
+    Float {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        geometry: Option<Rect>,
+    },
+    Mark(String),
+    MoveToWorkspace(String),
+    /// Call the code registered under this name by whatever runs the rules
+    Callback(String),
+}
+
+impl Rule {
+    pub fn matches(&self, data: &WindowData) -> bool {
+        self.on.contains(&data.change) && self.criteria.matches(&data.container)
+    }
+
+    /// Commands carrying out the actions on container `id`, callbacks aside
+    pub fn commands(&self, id: usize) -> Vec<Command> {
+        let on = |action: String| Command::with_criteria(Criteria::con_id(id), action);
+        let mut cmds = Vec::new();
+        for action in &self.actions {
+            match action {
+                Action::Command(cmd) => cmds.push(on(cmd.clone())),
+                Action::Float { geometry } => {
+                    cmds.push(on("floating enable".to_owned()));
+                    if let Some(r) = geometry {
+                        cmds.push(on(format!("resize set {} px {} px", r.width, r.height)));
+                        cmds.push(on(format!("move position {} px {} px", r.x, r.y)));
+                    }
+                }
+                Action::Mark(mark) => cmds.push(on(format!("mark --add {}", quote(mark)))),
+                Action::MoveToWorkspace(ws) => {
+                    cmds.push(on(format!("move container to workspace {}", quote(ws))))
+                }
+                Action::Callback(_) => {}
+            }
+        }
+        cmds
+    }
+
+    /// The rule's name, or its criteria if it has none
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("rule {name:?}"),
+            None => format!("rule {}", self.criteria),
+        }
+    }
+
+    /// Names of the callbacks to call, in order
+    pub fn callbacks(&self) -> impl Iterator<Item = &str> {
+        self.actions.iter().filter_map(|a| match a {
+            Action::Callback(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+}
+
+impl RuleSet {
+    pub fn from_json(s: &str) -> io::Result<Self> {
+        let rules: RuleSet = serde_json::from_str(s)?;
+        rules.check()?;
+        Ok(rules)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(s: &str) -> io::Result<Self> {
+        let rules: RuleSet =
+            toml::from_str(s).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        rules.check()?;
+        Ok(rules)
+    }
+
+    /// Every rule's commands must be possible to batch, see
+    /// [join](../command/fn.join.html). `InvalidData` naming the rule
+    /// otherwise.
+    pub fn check(&self) -> io::Result<()> {
+        for rule in &self.rules {
+            command::join(&rule.commands(0)).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{}: {e}", rule.label()))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Read a rules file, TOML if its name ends in `.toml` and JSON
+    /// otherwise. TOML without the `toml` feature is an `InvalidInput` error.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "toml") {
+            #[cfg(feature = "toml")]
+            return RuleSet::from_toml(&s);
+            #[cfg(not(feature = "toml"))]
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "reading TOML rules needs the toml feature",
+            ));
+        }
+        RuleSet::from_json(&s)
+    }
+
+    /// Rules matching `data`, in order
+    pub fn matching<'a>(&'a self, data: &'a WindowData) -> impl Iterator<Item = &'a Rule> {
+        self.rules.iter().filter(move |r| r.matches(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reply::{Node, WindowType};
+
+    const RULES: &str = r#"{"rules": [
+        {
+            "name": "float dialogs",
+            "criteria": {"window_type": "dialog"},
+            "actions": [{"float": {"geometry": {"x": 10, "y": 20, "width": 800, "height": 600}}}]
+        },
+        {
+            "criteria": {"class": "Firefox"},
+            "on": ["new", "title"],
+            "actions": [{"move_to_workspace": "2: web"}, {"mark": "browser"}, {"callback": "log"}]
+        }
+    ]}"#;
+
+    fn event(change: WindowChange, class: &str) -> WindowData {
+        let tree: Node =
+            serde_json::from_str(include_str!("../../i3ipc-types/test/tree.json")).unwrap();
+        let mut container = tree.find(|n| n.window.is_some()).unwrap().clone();
+        let props = container.window_properties.as_mut().unwrap();
+        props.class = Some(class.to_owned());
+        WindowData { change, container }
+    }
+
+    #[test]
+    fn test_matching() {
+        let rules = RuleSet::from_json(RULES).unwrap();
+        let new = event(WindowChange::New, "Firefox");
+        let matched: Vec<_> = rules.matching(&new).collect();
+        assert_eq!(matched.len(), 1);
+        let id = new.container.id;
+        assert_eq!(
+            matched[0]
+                .commands(id)
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                format!(r#"[con_id={id}] move container to workspace "2: web""#),
+                format!(r#"[con_id={id}] mark --add "browser""#),
+            ]
+        );
+        assert_eq!(matched[0].callbacks().collect::<Vec<_>>(), vec!["log"]);
+        assert_eq!(
+            rules
+                .matching(&event(WindowChange::Title, "Firefox"))
+                .count(),
+            1
+        );
+        assert_eq!(
+            rules
+                .matching(&event(WindowChange::Focus, "Firefox"))
+                .count(),
+            0
+        );
+
+        let mut dialog = event(WindowChange::New, "Zenity");
+        dialog.container.window_type = Some(WindowType::Dialog);
+        let float = rules.matching(&dialog).next().unwrap();
+        assert_eq!(float.name.as_deref(), Some("float dialogs"));
+        assert_eq!(
+            float.commands(1)[1].action,
+            "resize set 800 px 600 px".to_owned()
+        );
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml() {
+        let rules = RuleSet::from_toml(
+            r#"
+            [[rules]]
+            criteria = { class = "Firefox" }
+            actions = [{ float = {} }, { command = "border none" }]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(rules.rules[0].on, vec![WindowChange::New]);
+        assert_eq!(
+            rules.rules[0].actions,
+            vec![
+                Action::Float { geometry: None },
+                Action::Command("border none".into())
+            ]
+        );
+    }
+}
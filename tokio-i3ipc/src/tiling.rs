@@ -0,0 +1,573 @@
+//! dwm-style layouts imposed on i3 workspaces. A [Layout](trait.Layout.html)
+//! turns the windows of a workspace into an [Arrangement](enum.Arrangement.html),
+//! a tree of splits, and the [LayoutManager](struct.LayoutManager.html)
+//! works out the commands that bring the workspace into that shape.
+//!
+//! Windows keep the order they were first seen in, in tree order, with new
+//! ones going last; the first one is the master. When the workspace already has
+//! the right shape only the sizes are corrected; otherwise every window is
+//! moved into place using temporary marks, and i3 closes the containers
+//! left empty.
+use std::collections::HashMap;
+
+use crate::{
+    command::{quote, Command},
+    criteria::Criteria,
+    reply::{Node, NodeLayout, NodeType, Rect},
+};
+
+/// Prefix of the marks used while moving windows around
+const MARK: &str = "_i3ipc_tiling_";
+
+/// The shape a layout wants a workspace to have
+#[derive(PartialEq, Debug, Clone)]
+pub enum Arrangement {
+    /// A window, by container id
+    Window(usize),
+    Split {
+        /// One of `SplitH`, `SplitV`, `Tabbed` or `Stacked`
+        layout: NodeLayout,
+        children: Vec<Arrangement>,
+        /// Share of the split each child gets, between 0 and 1. Empty to
+        /// leave sizes to i3.
+        percents: Vec<f64>,
+    },
+}
+
+impl Arrangement {
+    /// A split of `children`, or the child itself if there is only one
+    pub fn split(layout: NodeLayout, mut children: Vec<Arrangement>) -> Self {
+        if children.len() == 1 {
+            return children.remove(0);
+        }
+        Arrangement::Split {
+            layout,
+            children,
+            percents: Vec::new(),
+        }
+    }
+
+    /// Size the children of a split, ignored on a window
+    pub fn with_percents(mut self, sizes: Vec<f64>) -> Self {
+        if let Arrangement::Split { percents, .. } = &mut self {
+            *percents = sizes;
+        }
+        self
+    }
+
+    /// The current shape of the tiled part of `node`. Containers with a
+    /// single child are skipped over, they make no difference to the
+    /// layout.
+    pub fn from_node(node: &Node) -> Option<Self> {
+        if node.window.is_some() {
+            return Some(Arrangement::Window(node.id));
+        }
+        let mut children = Vec::new();
+        let mut percents = Vec::new();
+        for n in &node.nodes {
+            if let Some(child) = Arrangement::from_node(n) {
+                children.push(child);
+                percents.push(n.percent.unwrap_or_default());
+            }
+        }
+        match children.len() {
+            0 => None,
+            1 => children.pop(),
+            _ => Some(Arrangement::Split {
+                layout: node.layout.clone(),
+                children,
+                percents,
+            }),
+        }
+    }
+
+    /// The first window, which stands in for the whole arrangement when
+    /// moving it around
+    pub fn head(&self) -> usize {
+        match self {
+            Arrangement::Window(id) => *id,
+            Arrangement::Split { children, .. } => children[0].head(),
+        }
+    }
+
+    /// Windows in order
+    pub fn windows(&self) -> Vec<usize> {
+        match self {
+            Arrangement::Window(id) => vec![*id],
+            Arrangement::Split { children, .. } => {
+                children.iter().flat_map(Arrangement::windows).collect()
+            }
+        }
+    }
+
+    /// Same windows in the same splits, whatever their sizes
+    pub fn same_shape(&self, other: &Arrangement) -> bool {
+        match (self, other) {
+            (Arrangement::Window(a), Arrangement::Window(b)) => a == b,
+            (
+                Arrangement::Split {
+                    layout: a,
+                    children: ac,
+                    ..
+                },
+                Arrangement::Split {
+                    layout: b,
+                    children: bc,
+                    ..
+                },
+            ) => a == b && ac.len() == bc.len() && ac.iter().zip(bc).all(|(a, b)| a.same_shape(b)),
+            _ => false,
+        }
+    }
+}
+
+/// A way of arranging windows
+pub trait Layout {
+    /// Arrange `windows`, never empty, the master first. `area` is the
+    /// workspace's rect.
+    fn arrange(&self, windows: &[usize], area: &Rect) -> Arrangement;
+}
+
+fn windows(ids: &[usize]) -> Vec<Arrangement> {
+    ids.iter().map(|id| Arrangement::Window(*id)).collect()
+}
+
+/// Masters side by side on the left, the rest stacked on the right
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct MasterStack {
+    pub masters: usize,
+    /// Share of the width the masters get
+    pub ratio: f64,
+}
+
+impl Default for MasterStack {
+    fn default() -> Self {
+        MasterStack {
+            masters: 1,
+            ratio: 0.55,
+        }
+    }
+}
+
+impl Layout for MasterStack {
+    fn arrange(&self, ids: &[usize], _area: &Rect) -> Arrangement {
+        let masters = self.masters.max(1);
+        if ids.len() <= masters {
+            return Arrangement::split(NodeLayout::SplitH, windows(ids));
+        }
+        let (master, stack) = ids.split_at(masters);
+        Arrangement::split(
+            NodeLayout::SplitH,
+            vec![
+                Arrangement::split(NodeLayout::SplitV, windows(master)),
+                Arrangement::split(NodeLayout::SplitV, windows(stack)),
+            ],
+        )
+        .with_percents(vec![self.ratio, 1.0 - self.ratio])
+    }
+}
+
+/// The master in the middle, the rest alternating between stacks on its
+/// right and left
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct CenteredMaster {
+    /// Share of the width the master gets
+    pub ratio: f64,
+}
+
+impl Default for CenteredMaster {
+    fn default() -> Self {
+        CenteredMaster { ratio: 0.5 }
+    }
+}
+
+impl Layout for CenteredMaster {
+    fn arrange(&self, ids: &[usize], area: &Rect) -> Arrangement {
+        if ids.len() < 3 {
+            return MasterStack {
+                masters: 1,
+                ratio: self.ratio,
+            }
+            .arrange(ids, area);
+        }
+        let (right, left): (Vec<_>, Vec<_>) =
+            ids[1..].iter().enumerate().partition(|(i, _)| i % 2 == 0);
+        let stack = |side: Vec<(usize, &usize)>| {
+            Arrangement::split(
+                NodeLayout::SplitV,
+                side.into_iter()
+                    .map(|(_, id)| Arrangement::Window(*id))
+                    .collect(),
+            )
+        };
+        let side = (1.0 - self.ratio) / 2.0;
+        Arrangement::split(
+            NodeLayout::SplitH,
+            vec![stack(left), Arrangement::Window(ids[0]), stack(right)],
+        )
+        .with_percents(vec![side, self.ratio, side])
+    }
+}
+
+/// Every window in one tabbed container
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Monocle;
+
+impl Layout for Monocle {
+    fn arrange(&self, ids: &[usize], _area: &Rect) -> Arrangement {
+        Arrangement::split(NodeLayout::Tabbed, windows(ids))
+    }
+}
+
+/// Each window takes half of the space left by the ones before it,
+/// alternating between horizontal and vertical splits
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Spiral;
+
+impl Layout for Spiral {
+    fn arrange(&self, ids: &[usize], area: &Rect) -> Arrangement {
+        fn spiral(ids: &[usize], horizontal: bool) -> Arrangement {
+            if ids.len() == 1 {
+                return Arrangement::Window(ids[0]);
+            }
+            let layout = if horizontal {
+                NodeLayout::SplitH
+            } else {
+                NodeLayout::SplitV
+            };
+            Arrangement::split(
+                layout,
+                vec![Arrangement::Window(ids[0]), spiral(&ids[1..], !horizontal)],
+            )
+        }
+        // start along the longer side
+        spiral(ids, area.width >= area.height)
+    }
+}
+
+/// Which layout each workspace gets. Workspaces without one are left alone.
+#[derive(Default)]
+pub struct LayoutManager {
+    layouts: HashMap<String, Box<dyn Layout + Send + Sync>>,
+    /// Windows of each workspace in the order they are handed to its layout
+    order: HashMap<String, Vec<usize>>,
+}
+
+impl LayoutManager {
+    pub fn new() -> Self {
+        LayoutManager::default()
+    }
+
+    pub fn set<L>(&mut self, workspace: &str, layout: L)
+    where
+        L: Layout + Send + Sync + 'static,
+    {
+        self.layouts.insert(workspace.to_owned(), Box::new(layout));
+    }
+
+    pub fn remove(&mut self, workspace: &str) {
+        self.layouts.remove(workspace);
+        self.order.remove(workspace);
+    }
+
+    pub fn layout(&self, workspace: &str) -> Option<&(dyn Layout + Send + Sync)> {
+        self.layouts.get(workspace).map(|l| &**l)
+    }
+
+    /// Commands bringing every managed workspace in `tree` into its layout
+    pub fn plan(&mut self, tree: &Node) -> Vec<Command> {
+        let mut cmds = Vec::new();
+        let mut stack = vec![tree];
+        while let Some(node) = stack.pop() {
+            if node.node_type == NodeType::Workspace {
+                cmds.extend(self.plan_workspace(node));
+            } else {
+                stack.extend(node.nodes.iter().rev());
+            }
+        }
+        cmds
+    }
+
+    /// Commands bringing workspace `ws` into its layout, none if it has no
+    /// layout or is already arranged
+    pub fn plan_workspace(&mut self, ws: &Node) -> Vec<Command> {
+        let (Some(name), Some(current)) = (ws.name.as_deref(), Arrangement::from_node(ws)) else {
+            return Vec::new();
+        };
+        let Some(layout) = self.layouts.get(name) else {
+            return Vec::new();
+        };
+        let present = current.windows();
+        let order = self.order.entry(name.to_owned()).or_default();
+        order.retain(|id| present.contains(id));
+        for id in present {
+            if !order.contains(&id) {
+                order.push(id);
+            }
+        }
+        let desired = layout.arrange(order, &ws.rect);
+        let mut cmds = Vec::new();
+        if desired.same_shape(&current) {
+            resize(&desired, Some(&current), &mut cmds);
+        } else {
+            rebuild(ws, &desired, &mut cmds);
+            resize(&desired, None, &mut cmds);
+        }
+        cmds
+    }
+}
+
+fn on(id: usize, action: String) -> Command {
+    Command::with_criteria(Criteria::con_id(id), action)
+}
+
+fn layout_name(layout: &NodeLayout) -> &str {
+    match layout {
+        NodeLayout::SplitH => "splith",
+        NodeLayout::SplitV => "splitv",
+        NodeLayout::Tabbed => "tabbed",
+        NodeLayout::Stacked => "stacking",
+        other => other.as_str(),
+    }
+}
+
+/// Move `id` right after `target`, which must be a window
+fn move_after(target: usize, id: usize, cmds: &mut Vec<Command>) {
+    let mark = quote(&format!("{MARK}{target}"));
+    cmds.push(on(target, format!("mark --add {mark}")));
+    cmds.push(on(id, format!("move window to mark {mark}")));
+    cmds.push(Command::new(format!("unmark {mark}")));
+}
+
+fn rebuild(ws: &Node, desired: &Arrangement, cmds: &mut Vec<Command>) {
+    let head = desired.head();
+    if !ws.nodes.iter().any(|n| n.id == head) {
+        // moving to a mark on a split puts the window next to its focused
+        // child, for a workspace that is its top level
+        let mark = quote(&format!("{MARK}{}", ws.id));
+        cmds.push(on(ws.id, format!("mark --add {mark}")));
+        cmds.push(on(head, format!("move window to mark {mark}")));
+        cmds.push(Command::new(format!("unmark {mark}")));
+    }
+    if let Arrangement::Split {
+        layout, children, ..
+    } = desired
+    {
+        // the head's parent is now the workspace
+        cmds.push(on(head, format!("layout {}", layout_name(layout))));
+        build_children(children, cmds);
+    }
+}
+
+/// Put the head of every child after the head of the one before, then build
+/// each child in place
+fn build_children(children: &[Arrangement], cmds: &mut Vec<Command>) {
+    for pair in children.windows(2) {
+        move_after(pair[0].head(), pair[1].head(), cmds);
+    }
+    for child in children {
+        build(child, cmds);
+    }
+}
+
+/// Turn the head of `arr`, a window among its future siblings, into the
+/// whole of `arr`
+fn build(arr: &Arrangement, cmds: &mut Vec<Command>) {
+    let Arrangement::Split {
+        layout, children, ..
+    } = arr
+    else {
+        return;
+    };
+    let head = arr.head();
+    let split = match layout {
+        NodeLayout::SplitV | NodeLayout::Stacked => "split v",
+        _ => "split h",
+    };
+    cmds.push(on(head, split.to_owned()));
+    if matches!(layout, NodeLayout::Tabbed | NodeLayout::Stacked) {
+        cmds.push(on(head, format!("layout {}", layout_name(layout))));
+    }
+    build_children(children, cmds);
+}
+
+/// Size the splits of `desired`. With `current`, of the same shape, only the
+/// sizes that differ are set.
+fn resize(desired: &Arrangement, current: Option<&Arrangement>, cmds: &mut Vec<Command>) {
+    let Arrangement::Split {
+        layout,
+        children,
+        percents,
+    } = desired
+    else {
+        return;
+    };
+    let (current_children, current_percents) = match current {
+        Some(Arrangement::Split {
+            children, percents, ..
+        }) => (Some(children), Some(percents)),
+        _ => (None, None),
+    };
+    let dimension = match layout {
+        NodeLayout::SplitH => Some("width"),
+        NodeLayout::SplitV => Some("height"),
+        _ => None,
+    };
+    if let Some(dimension) = dimension {
+        // the last child gets what is left
+        for (i, (child, want)) in children.iter().zip(percents).enumerate() {
+            let have = current_percents.and_then(|p| p.get(i));
+            if i + 1 == children.len() || have.is_some_and(|have| (have - want).abs() < 0.01) {
+                continue;
+            }
+            cmds.push(on(
+                child.head(),
+                format!(
+                    "resize set {dimension} {} ppt",
+                    (want * 100.0).round() as u32
+                ),
+            ));
+        }
+    }
+    for (i, child) in children.iter().enumerate() {
+        resize(child, current_children.and_then(|c| c.get(i)), cmds);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // workspace 7: splith [ VSCODE, splitv [ GHCI, SHELL ] ], evenly sized
+    const VSCODE: usize = 106996225509760;
+    const GHCI: usize = 106996225511040;
+    const SHELL: usize = 106996225510400;
+
+    fn workspace() -> Node {
+        let tree: Node =
+            serde_json::from_str(include_str!("../../i3ipc-types/test/tree.json")).unwrap();
+        tree.find_workspace("7").unwrap().clone()
+    }
+
+    fn actions(cmds: Vec<Command>) -> Vec<String> {
+        cmds.into_iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn test_layouts() {
+        let area = Rect::new(0, 0, 1920, 1080);
+        let w = Arrangement::Window;
+        assert_eq!(
+            MasterStack::default().arrange(&[1, 2, 3], &area),
+            Arrangement::split(
+                NodeLayout::SplitH,
+                vec![
+                    w(1),
+                    Arrangement::split(NodeLayout::SplitV, vec![w(2), w(3)])
+                ]
+            )
+            .with_percents(vec![0.55, 1.0 - 0.55])
+        );
+        assert_eq!(MasterStack::default().arrange(&[1], &area), w(1));
+        let centered = CenteredMaster::default().arrange(&[1, 2, 3, 4], &area);
+        assert_eq!(centered.windows(), vec![3, 1, 2, 4]);
+        assert_eq!(
+            Monocle.arrange(&[1, 2], &area),
+            Arrangement::split(NodeLayout::Tabbed, vec![w(1), w(2)])
+        );
+        assert_eq!(
+            Spiral.arrange(&[1, 2, 3], &area),
+            Arrangement::split(
+                NodeLayout::SplitH,
+                vec![
+                    w(1),
+                    Arrangement::split(NodeLayout::SplitV, vec![w(2), w(3)])
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn test_already_arranged() {
+        let ws = workspace();
+        let mut manager = LayoutManager::new();
+        manager.set("7", MasterStack::default());
+        // only the master needs resizing
+        assert_eq!(
+            actions(manager.plan_workspace(&ws)),
+            vec![format!("[con_id={VSCODE}] resize set width 55 ppt")]
+        );
+        manager.set(
+            "7",
+            MasterStack {
+                masters: 1,
+                ratio: 0.5,
+            },
+        );
+        assert!(manager.plan_workspace(&ws).is_empty());
+        manager.remove("7");
+        assert!(manager.plan_workspace(&ws).is_empty());
+    }
+
+    #[test]
+    fn test_order_is_kept() {
+        let mut ws = workspace();
+        let mut manager = LayoutManager::new();
+        manager.set("7", CenteredMaster::default());
+        assert!(!manager.plan_workspace(&ws).is_empty());
+        assert_eq!(manager.order["7"], vec![VSCODE, GHCI, SHELL]);
+        // once arranged the tree order is [SHELL, VSCODE, GHCI], the master
+        // stays the same
+        ws.nodes[1].nodes.reverse();
+        ws.nodes.reverse();
+        manager.plan_workspace(&ws);
+        assert_eq!(manager.order["7"], vec![VSCODE, GHCI, SHELL]);
+    }
+
+    #[test]
+    fn test_rebuild() {
+        let ws = workspace();
+        let mut manager = LayoutManager::new();
+        manager.set("7", Monocle);
+        let mark = |id: usize| format!("\"{MARK}{id}\"");
+        assert_eq!(
+            actions(manager.plan_workspace(&ws)),
+            vec![
+                format!("[con_id={VSCODE}] layout tabbed"),
+                format!("[con_id={VSCODE}] mark --add {}", mark(VSCODE)),
+                format!("[con_id={GHCI}] move window to mark {}", mark(VSCODE)),
+                format!("unmark {}", mark(VSCODE)),
+                format!("[con_id={GHCI}] mark --add {}", mark(GHCI)),
+                format!("[con_id={SHELL}] move window to mark {}", mark(GHCI)),
+                format!("unmark {}", mark(GHCI)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rebuild_nested() {
+        // splith [ VSCODE, GHCI, SHELL ]
+        let mut ws = workspace();
+        let stack = ws.nodes.remove(1);
+        ws.nodes.extend(stack.nodes);
+        let mut manager = LayoutManager::new();
+        manager.set("7", MasterStack::default());
+        let mark = |id: usize| format!("\"{MARK}{id}\"");
+        assert_eq!(
+            actions(manager.plan_workspace(&ws)),
+            vec![
+                format!("[con_id={VSCODE}] layout splith"),
+                // GHCI after the master, then split to hold the stack
+                format!("[con_id={VSCODE}] mark --add {}", mark(VSCODE)),
+                format!("[con_id={GHCI}] move window to mark {}", mark(VSCODE)),
+                format!("unmark {}", mark(VSCODE)),
+                format!("[con_id={GHCI}] split v"),
+                format!("[con_id={GHCI}] mark --add {}", mark(GHCI)),
+                format!("[con_id={SHELL}] move window to mark {}", mark(GHCI)),
+                format!("unmark {}", mark(GHCI)),
+                // the new stack is split evenly already
+                format!("[con_id={VSCODE}] resize set width 55 ppt"),
+            ]
+        );
+    }
+}
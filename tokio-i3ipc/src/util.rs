@@ -11,15 +11,13 @@ where
     F: Fn(u32, Vec<u8>) -> T,
     S: AsyncRead + Unpin,
 {
-    let mut buf = [0; 14];
+    let mut buf = [0; frame::HEADER_LEN];
     stream.read_exact(&mut buf).await?;
-    assert!(&buf[0..6] == MAGIC.as_bytes(), "Magic str not received");
-    let payload_len = u32::from_ne_bytes([buf[6], buf[7], buf[8], buf[9]]) as usize;
-    let msg_type = u32::from_ne_bytes([buf[10], buf[11], buf[12], buf[13]]);
+    let header = frame::Header::parse(&buf)?;
 
-    let mut payload = vec![0; payload_len];
+    let mut payload = vec![0; header.len as usize];
     stream.read_exact(&mut payload).await?;
-    Ok(f(msg_type, payload))
+    Ok(f(header.msg_type, payload))
 }
 
 /// Decode a response into a [MsgResponse](struct.MsgResponse.html)
@@ -39,3 +37,25 @@ where
 {
     decode_response(stream, decode_event).await
 }
+
+/// Turn the first failed command in a `RunCommand` reply into an error
+pub(crate) fn check_success(results: Vec<reply::Success>) -> stio::Result<()> {
+    match results.into_iter().find(|r| !r.success) {
+        Some(failed) => Err(stio::Error::new(
+            stio::ErrorKind::InvalidInput,
+            failed.error.unwrap_or_else(|| "command failed".to_owned()),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Turn the first failed command of a batch into an error
+pub(crate) fn check_results(results: &[command::CommandResult]) -> stio::Result<()> {
+    match results.iter().find_map(|r| Some((r, r.error()?))) {
+        Some((failed, error)) => Err(stio::Error::new(
+            stio::ErrorKind::InvalidInput,
+            format!("{}: {error}", failed.command),
+        )),
+        None => Ok(()),
+    }
+}
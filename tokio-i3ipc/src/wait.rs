@@ -0,0 +1,84 @@
+//! Waiting on the event stream with a deadline, e.g. "until workspace 2 is
+//! focused or two seconds pass". Every method returns an `ErrorKind::TimedOut`
+//! error once `deadline` passes and `UnexpectedEof` if the stream ends first.
+use std::{io, time::Instant};
+
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{
+    criteria::Criteria,
+    event::{Event, WorkspaceChange},
+    reply,
+};
+
+/// Adds `wait_for*` to any stream of events, like the one returned by
+/// [listen](../struct.I3.html#method.listen)
+#[allow(async_fn_in_trait)]
+pub trait EventStreamExt: Stream<Item = io::Result<Event>> + Unpin + Sized {
+    /// Wait for the first event `predicate` returns true for
+    async fn wait_for<F>(&mut self, mut predicate: F, deadline: Instant) -> io::Result<Event>
+    where
+        F: FnMut(&Event) -> bool,
+    {
+        wait_map(self, |evt| predicate(&evt).then_some(evt), deadline).await
+    }
+
+    /// Wait for a window event whose container matches `criteria`, returning
+    /// the container. Needs a subscription to `Subscribe::Window`.
+    async fn wait_for_window(
+        &mut self,
+        criteria: &Criteria,
+        deadline: Instant,
+    ) -> io::Result<reply::Node> {
+        wait_map(self, |evt| window_matching(evt, criteria), deadline).await
+    }
+
+    /// Wait for the workspace called `name` to be focused, returning it. Needs
+    /// a subscription to `Subscribe::Workspace`.
+    async fn wait_for_workspace(
+        &mut self,
+        name: &str,
+        deadline: Instant,
+    ) -> io::Result<reply::Node> {
+        wait_map(self, |evt| workspace_focused(evt, name), deadline).await
+    }
+}
+
+impl<S: Stream<Item = io::Result<Event>> + Unpin> EventStreamExt for S {}
+
+async fn wait_map<S, T, F>(events: &mut S, mut f: F, deadline: Instant) -> io::Result<T>
+where
+    S: Stream<Item = io::Result<Event>> + Unpin,
+    F: FnMut(Event) -> Option<T>,
+{
+    let wait = async {
+        while let Some(evt) = events.next().await {
+            if let Some(found) = f(evt?) {
+                return Ok(found);
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "event stream closed",
+        ))
+    };
+    tokio::time::timeout_at(deadline.into(), wait)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "deadline passed"))?
+}
+
+fn window_matching(evt: Event, criteria: &Criteria) -> Option<reply::Node> {
+    match evt {
+        Event::Window(w) if criteria.matches(&w.container) => Some(w.container),
+        _ => None,
+    }
+}
+
+fn workspace_focused(evt: Event, name: &str) -> Option<reply::Node> {
+    match evt {
+        Event::Workspace(w) if w.change == WorkspaceChange::Focus => {
+            w.current.filter(|n| n.name.as_deref() == Some(name))
+        }
+        _ => None,
+    }
+}
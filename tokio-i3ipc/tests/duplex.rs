@@ -0,0 +1,566 @@
+use std::time::{Duration, Instant};
+use tokio::{
+    io::{duplex, AsyncRead, AsyncReadExt, AsyncWriteExt, DuplexStream},
+    net::UnixListener,
+    task::JoinHandle,
+};
+use tokio_i3ipc::{
+    autotile::Autotiler,
+    event::Event,
+    focus_history::FocusHistory,
+    handle::{WindowRef, WorkspaceRef},
+    msg::Msg,
+    rebalance::Rebalancer,
+    request,
+    rule_engine::RuleEngine,
+    session::Session,
+    tiling::{LayoutManager, MasterStack},
+    EventStreamExt, I3, MAGIC,
+};
+
+fn frame(msg_type: u32, payload: &str) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend(MAGIC.as_bytes());
+    buf.extend((payload.len() as u32).to_ne_bytes());
+    buf.extend(msg_type.to_ne_bytes());
+    buf.extend(payload.as_bytes());
+    buf
+}
+
+async fn read_request<S: AsyncRead + Unpin>(server: &mut S) -> (u32, String) {
+    let mut header = [0_u8; 14];
+    server.read_exact(&mut header).await.unwrap();
+    assert_eq!(&header[..6], MAGIC.as_bytes());
+    let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]) as usize;
+    let msg_type = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+    let mut payload = vec![0_u8; len];
+    server.read_exact(&mut payload).await.unwrap();
+    (msg_type, String::from_utf8(payload).unwrap())
+}
+
+/// A fake i3 answering requests in the order they are scripted. Each step
+/// names the message type it expects, the payload if it matters and the
+/// reply; the server task panics on anything else.
+#[derive(Default)]
+struct Mock(Vec<(Msg, Option<String>, String)>);
+
+impl Mock {
+    /// Expect a `msg` request with any payload and answer `reply`
+    fn reply(mut self, msg: Msg, reply: impl Into<String>) -> Self {
+        self.0.push((msg, None, reply.into()));
+        self
+    }
+
+    /// Expect a `msg` request with exactly `payload` and answer `reply`
+    fn expect(mut self, msg: Msg, payload: impl Into<String>, reply: impl Into<String>) -> Self {
+        self.0.push((msg, Some(payload.into()), reply.into()));
+        self
+    }
+
+    /// Connect an `I3` to the script, returning it with the server task.
+    /// The server hangs up once the script is done.
+    fn start(self) -> (I3<DuplexStream>, JoinHandle<()>) {
+        let (client, mut server) = duplex(1 << 20);
+        let server = tokio::spawn(async move {
+            for (msg, expected, reply) in self.0 {
+                let (msg_type, payload) = read_request(&mut server).await;
+                assert_eq!(msg_type, u32::from(msg), "request {payload:?}");
+                if let Some(expected) = expected {
+                    assert_eq!(payload, expected);
+                }
+                server.write_all(&frame(msg_type, &reply)).await.unwrap();
+            }
+        });
+        (I3::from_stream(client), server)
+    }
+}
+
+/// A `RunCommand` reply with `n` successes
+fn ok(n: usize) -> String {
+    format!("[{}]", vec![r#"{"success":true}"#; n].join(","))
+}
+
+fn tree() -> &'static str {
+    include_str!("../../i3ipc-types/test/tree.json")
+}
+
+fn output_json(name: &str, x: u32, current: &str) -> String {
+    format!(
+        r#"{{"name":"{name}","active":true,"primary":false,"current_workspace":"{current}","rect":{{"x":{x},"y":0,"width":1920,"height":1080}}}}"#
+    )
+}
+
+fn workspace_json(name: &str, output: &str, visible: bool, focused: bool) -> String {
+    format!(
+        r#"{{"num":-1,"name":"{name}","visible":{visible},"focused":{focused},"urgent":false,"output":"{output}","rect":{{"x":0,"y":0,"width":0,"height":0}},"focus":[]}}"#
+    )
+}
+
+fn window_event(change: &str) -> Event {
+    let tree: tokio_i3ipc::reply::Node = serde_json::from_str(tree()).unwrap();
+    // the Firefox window on workspace 2
+    let container = tree.find(|n| n.id == 106996225532160).unwrap();
+    Event::Window(Box::new(
+        serde_json::from_value(serde_json::json!({ "change": change, "container": container }))
+            .unwrap(),
+    ))
+}
+
+#[tokio::test]
+async fn test_run_command_over_duplex() {
+    let (mut i3, server) = Mock::default()
+        .expect(Msg::RunCommand, "nop", ok(1))
+        .start();
+
+    let resp = i3.run_command("nop").await.unwrap();
+    assert_eq!(resp.len(), 1);
+    assert!(resp[0].success);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_get_version_over_duplex() {
+    let version = include_str!("../../i3ipc-types/test/version.json");
+    let (mut i3, server) = Mock::default().expect(Msg::Version, "", version).start();
+
+    let version = i3.get_version().await.unwrap();
+    assert_eq!(version.major, 4);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_get_binding_state_sends_binding_state() {
+    let (mut i3, server) = Mock::default()
+        .reply(Msg::BindingState, r#"{"name":"resize"}"#)
+        .start();
+
+    let state = i3.get_binding_state().await.unwrap();
+    assert_eq!(state.name, "resize");
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_mismatched_reply_is_error() {
+    let (client, mut server) = duplex(1024);
+    let mut i3 = I3::from_stream(client);
+
+    let server = tokio::spawn(async move {
+        read_request(&mut server).await;
+        // answer a Tree request with a Version reply
+        let version = include_str!("../../i3ipc-types/test/version.json");
+        server
+            .write_all(&frame(u32::from(Msg::Version), version))
+            .await
+            .unwrap();
+    });
+
+    let err = i3.request(request::Tree).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_oversized_reply_is_error() {
+    let (mut i3, server) = Mock::default().reply(Msg::RunCommand, ok(1)).start();
+    i3.set_max_frame_len(8);
+
+    let err = i3.run_command("nop").await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_barrier_returns_events_before_its_tick() {
+    let (client, mut server) = duplex(1024);
+    let (events_client, mut events_server) = duplex(1024);
+    let mut i3 = I3::from_stream(client);
+    let mut events = I3::from_stream(events_client).listen();
+
+    let server = tokio::spawn(async move {
+        let (msg_type, payload) = read_request(&mut server).await;
+        assert_eq!(msg_type, u32::from(Msg::Tick));
+        let tick = |payload: &str| {
+            frame(
+                7 | 1 << 31,
+                &format!(r#"{{"first":false,"payload":"{payload}"}}"#),
+            )
+        };
+        events_server.write_all(&tick("other")).await.unwrap();
+        events_server
+            .write_all(&frame(
+                2 | 1 << 31,
+                r#"{"change":"resize","pango_markup":false}"#,
+            ))
+            .await
+            .unwrap();
+        events_server.write_all(&tick(&payload)).await.unwrap();
+        server
+            .write_all(&frame(msg_type, r#"{"success":true}"#))
+            .await
+            .unwrap();
+        events_server
+    });
+
+    let seen = i3.barrier(&mut events).await.unwrap();
+    assert_eq!(seen.len(), 2);
+    assert!(matches!(&seen[0], Event::Tick(t) if t.payload == "other"));
+    assert!(matches!(&seen[1], Event::Mode(_)));
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_barrier_fails_on_rejected_tick() {
+    let (mut i3, server) = Mock::default()
+        .reply(Msg::Tick, r#"{"success":false}"#)
+        .start();
+    let (events_client, _events_server) = duplex(1024);
+    let mut events = I3::from_stream(events_client).listen();
+
+    let err = i3.barrier(&mut events).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_run_and_wait_window() {
+    let path = std::env::temp_dir().join(format!("tokio-i3ipc-test-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut cmd, _) = listener.accept().await.unwrap();
+        let (mut sub, _) = listener.accept().await.unwrap();
+        let (msg_type, payload) = read_request(&mut sub).await;
+        assert_eq!(msg_type, u32::from(Msg::Subscribe));
+        assert!(payload.contains("window"));
+        sub.write_all(&frame(msg_type, r#"{"success":true}"#))
+            .await
+            .unwrap();
+
+        let (msg_type, payload) = read_request(&mut cmd).await;
+        assert_eq!(payload, "exec xterm");
+        cmd.write_all(&frame(msg_type, &ok(1))).await.unwrap();
+        let tree: tokio_i3ipc::reply::Node = serde_json::from_str(tree()).unwrap();
+        // focus on Firefox, then a new GHCI window
+        for (change, id) in [("focus", 106996225532160), ("new", 106996225511040)] {
+            let container = tree.find(|n| n.id == id).unwrap();
+            let evt = serde_json::json!({ "change": change, "container": container });
+            sub.write_all(&frame(3 | 1 << 31, &evt.to_string()))
+                .await
+                .unwrap();
+        }
+        // keep both connections open until the client is done
+        let _ = sub.read(&mut [0]).await;
+    });
+
+    let mut i3 = I3::connect_to(&path).await.unwrap();
+    let node = i3
+        .run_and_wait_window("exec xterm", |_| true, Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(node.id, 106996225511040);
+    server.abort();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[tokio::test]
+async fn test_wait_for_workspace() {
+    let (events_client, mut events_server) = duplex(1 << 20);
+    let mut events = I3::from_stream(events_client).listen();
+    let node: tokio_i3ipc::reply::Node = serde_json::from_str(tree()).unwrap();
+    let name = node.name.clone().unwrap();
+    for change in ["init", "focus"] {
+        let evt = format!(r#"{{"change":"{change}","current":{},"old":null}}"#, tree());
+        events_server
+            .write_all(&frame(1 << 31, &evt))
+            .await
+            .unwrap();
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let ws = events.wait_for_workspace(&name, deadline).await.unwrap();
+    assert_eq!(ws.id, node.id);
+
+    let deadline = Instant::now() + Duration::from_millis(10);
+    let err = events.wait_for(|_| true, deadline).await.unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[tokio::test]
+async fn test_window_ref_commands() {
+    let node: tokio_i3ipc::reply::Node = serde_json::from_str(tree()).unwrap();
+    let id = node.id;
+    let (mut i3, server) = Mock::default()
+        .expect(
+            Msg::RunCommand,
+            format!(r#"[con_id={id}] move container to workspace "3: \"web\"""#),
+            ok(1),
+        )
+        .expect(
+            Msg::RunCommand,
+            format!("[con_id={id}] kill"),
+            r#"[{"success":false,"error":"No window matches"}]"#,
+        )
+        .start();
+
+    let win = WindowRef::new(node);
+    win.move_to_workspace(&mut i3, r#"3: "web""#).await.unwrap();
+    let err = win.kill(&mut i3).await.unwrap_err();
+    assert_eq!(err.to_string(), "No window matches");
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_workspace_ref_move_to_output() {
+    let ws: tokio_i3ipc::reply::Workspace =
+        serde_json::from_str(&workspace_json("4", "DP-1", false, false)).unwrap();
+    // by focus rather than criteria, which can't match an empty workspace
+    let (mut i3, server) = Mock::default()
+        .expect(
+            Msg::RunCommand,
+            r#"workspace --no-auto-back-and-forth "4"; move workspace to output "HDMI-1""#,
+            ok(2),
+        )
+        .start();
+
+    WorkspaceRef::from(ws)
+        .move_to_output(&mut i3, "HDMI-1")
+        .await
+        .unwrap();
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_rebalance_moves_workspace_back() {
+    let mut rebalancer = Rebalancer::default();
+    rebalancer.preferred.insert("2".into(), "DP-1".into());
+    let outputs = format!(
+        "[{},{}]",
+        output_json("eDP-1", 0, "1"),
+        output_json("DP-1", 1920, "3")
+    );
+    let before = format!(
+        "[{},{}]",
+        workspace_json("1", "eDP-1", true, true),
+        workspace_json("2", "eDP-1", false, false)
+    );
+    let after = format!(
+        "[{},{}]",
+        workspace_json("1", "eDP-1", true, true),
+        workspace_json("2", "DP-1", false, false)
+    );
+    let (mut i3, server) = Mock::default()
+        .reply(Msg::Outputs, &outputs)
+        .reply(Msg::Workspaces, before)
+        .expect(
+            Msg::RunCommand,
+            r#"workspace --no-auto-back-and-forth "2"; move workspace to output "DP-1"; workspace --no-auto-back-and-forth "1""#,
+            ok(3),
+        )
+        .reply(Msg::Outputs, &outputs)
+        .reply(Msg::Workspaces, after)
+        .start();
+
+    let results = i3.rebalance(&mut rebalancer).await.unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(rebalancer.preferred["1"], "eDP-1");
+    assert_eq!(rebalancer.preferred["2"], "DP-1");
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_run_rebalancer_waits_for_output_event() {
+    let mut rebalancer = Rebalancer::default();
+    rebalancer.preferred.insert("2".into(), "HDMI-1".into());
+    let laptop = format!("[{}]", output_json("eDP-1", 0, "1"));
+    let docked = format!(
+        "[{},{}]",
+        output_json("eDP-1", 0, "1"),
+        output_json("HDMI-1", 1920, "3")
+    );
+    let undocked = format!(
+        "[{},{}]",
+        workspace_json("1", "eDP-1", true, true),
+        workspace_json("2", "eDP-1", false, false)
+    );
+    // i3 made workspace 3 for the new output and sent its `init` first
+    let plugged = format!(
+        "[{},{},{}]",
+        workspace_json("1", "eDP-1", true, true),
+        workspace_json("2", "eDP-1", false, false),
+        workspace_json("3", "HDMI-1", true, false)
+    );
+    let moved = format!(
+        "[{},{},{}]",
+        workspace_json("1", "eDP-1", true, true),
+        workspace_json("2", "HDMI-1", false, false),
+        workspace_json("3", "HDMI-1", true, false)
+    );
+    let (mut i3, server) = Mock::default()
+        // the initial rebalance has nothing to move
+        .reply(Msg::Outputs, &laptop)
+        .reply(Msg::Workspaces, &undocked)
+        .reply(Msg::Outputs, &laptop)
+        .reply(Msg::Workspaces, &undocked)
+        // the `init` event
+        .reply(Msg::Outputs, &docked)
+        .reply(Msg::Workspaces, &plugged)
+        // the output event
+        .reply(Msg::Outputs, &docked)
+        .reply(Msg::Workspaces, &plugged)
+        .expect(
+            Msg::RunCommand,
+            r#"workspace --no-auto-back-and-forth "2"; move workspace to output "HDMI-1"; workspace --no-auto-back-and-forth "3"; workspace --no-auto-back-and-forth "1""#,
+            ok(4),
+        )
+        .reply(Msg::Outputs, &docked)
+        .reply(Msg::Workspaces, moved)
+        .start();
+
+    let init = Event::Workspace(Box::new(
+        serde_json::from_value(serde_json::json!({
+            "change": "init",
+            "current": null,
+            "old": null,
+        }))
+        .unwrap(),
+    ));
+    let output = Event::Output(serde_json::from_str(r#"{"change":"unspecified"}"#).unwrap());
+    let mut events = tokio_stream::iter(vec![Ok(init), Ok(output)]);
+    i3.run_rebalancer(&mut rebalancer, &mut events)
+        .await
+        .unwrap();
+    server.await.unwrap();
+    assert_eq!(rebalancer.preferred["1"], "eDP-1");
+    assert_eq!(rebalancer.preferred["2"], "HDMI-1");
+    assert_eq!(rebalancer.preferred["3"], "HDMI-1");
+}
+
+#[tokio::test]
+async fn test_restore_session_skips_missing_outputs() {
+    let session: Session = serde_json::from_value(serde_json::json!({
+        "workspaces": [
+            {"name": "1", "output": "DP-1", "visible": true, "focused": true, "layout": []},
+            {"name": "2", "output": "HDMI-1", "visible": false, "focused": false, "layout": []},
+        ]
+    }))
+    .unwrap();
+    // one command at a time, stopping at the first failure; HDMI-1 isn't
+    // connected, so workspace 2 isn't moved
+    let (mut i3, server) = Mock::default()
+        .reply(Msg::Outputs, format!("[{}]", output_json("DP-1", 0, "1")))
+        .expect(
+            Msg::RunCommand,
+            r#"workspace --no-auto-back-and-forth "1""#,
+            ok(1),
+        )
+        .expect(Msg::RunCommand, r#"move workspace to output "DP-1""#, ok(1))
+        .expect(
+            Msg::RunCommand,
+            r#"workspace --no-auto-back-and-forth "2""#,
+            ok(1),
+        )
+        .expect(
+            Msg::RunCommand,
+            r#"workspace --no-auto-back-and-forth "1""#,
+            ok(1),
+        )
+        .start();
+
+    let dir = std::env::temp_dir().join(format!("restore-session-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    i3.restore_session(&session, &dir).await.unwrap();
+    server.await.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_run_focus_history() {
+    let (mut i3, server) = Mock::default().reply(Msg::Tree, tree()).start();
+
+    let history = std::sync::Mutex::new(FocusHistory::new());
+    let mut events = tokio_stream::iter(vec![Ok(window_event("focus"))]);
+    i3.run_focus_history(&history, &mut events).await.unwrap();
+    server.await.unwrap();
+    let history = history.into_inner().unwrap();
+    assert_eq!(history.global()[0], 106996225532160);
+    assert!(history.global().len() > 1);
+}
+
+#[tokio::test]
+async fn test_autotile_splits_by_aspect_ratio() {
+    // tall window on workspace 7
+    let vscode = 106996225509760_usize;
+    let (mut i3, server) = Mock::default()
+        .reply(Msg::Tree, tree())
+        .expect(Msg::RunCommand, format!("[con_id={vscode}] split v"), ok(1))
+        .start();
+
+    let result = i3.autotile(&Autotiler::default(), vscode).await.unwrap();
+    assert!(result.unwrap().is_success());
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_apply_layouts_resizes_master() {
+    let mut manager = LayoutManager::new();
+    // workspace 7 is already a master and a stack, split evenly
+    manager.set(
+        "7",
+        MasterStack {
+            masters: 1,
+            ratio: 0.6,
+        },
+    );
+    let (mut i3, server) = Mock::default()
+        .reply(Msg::Tree, tree())
+        .expect(
+            Msg::RunCommand,
+            "[con_id=106996225509760] resize set width 60 ppt",
+            ok(1),
+        )
+        .start();
+
+    let results = i3.apply_layouts(&mut manager).await.unwrap();
+    assert_eq!(results.len(), 1);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_run_rules_with_callback() {
+    let rules = r#"{"rules": [{
+        "criteria": {"class": "Firefox"},
+        "actions": [{"mark": "web"}, {"callback": "title"}]
+    }]}"#;
+    let mut engine = RuleEngine::new(tokio_i3ipc::rules::RuleSet::from_json(rules).unwrap());
+    engine.callback("title", |win| {
+        vec![format!("[con_id={}] title_format \"web\"", win.container.id).into()]
+    });
+    let (mut i3, server) = Mock::default()
+        .expect(
+            Msg::RunCommand,
+            r#"[con_id=106996225532160] mark --add "web"; [con_id=106996225532160] title_format "web""#,
+            ok(2),
+        )
+        .start();
+
+    // only "new" events match by default
+    let mut events = tokio_stream::iter(vec![Ok(window_event("focus")), Ok(window_event("new"))]);
+    i3.run_rules(&mut engine, &mut events).await.unwrap();
+    server.await.unwrap();
+
+    // a dry run sends nothing
+    engine.set_dry_run(true);
+    let mut events = tokio_stream::iter(vec![Ok(window_event("new"))]);
+    i3.run_rules(&mut engine, &mut events).await.unwrap();
+
+    // an unknown callback or an unbatchable command skips the event
+    engine.set_dry_run(false);
+    engine.callback("title", |_| vec!["focus left, kill".into()]);
+    let mut events = tokio_stream::iter(vec![Ok(window_event("new"))]);
+    i3.run_rules(&mut engine, &mut events).await.unwrap();
+    let rules = r#"{"rules": [{"criteria": {}, "actions": [{"callback": "missing"}]}]}"#;
+    let mut engine = RuleEngine::new(tokio_i3ipc::rules::RuleSet::from_json(rules).unwrap());
+    let mut events = tokio_stream::iter(vec![Ok(window_event("new"))]);
+    i3.run_rules(&mut engine, &mut events).await.unwrap();
+}